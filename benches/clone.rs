@@ -0,0 +1,25 @@
+// Copyright (c) 2022 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use hostlist_parser::Hostlist;
+
+/// Cloning shares the ranges, so its cost should not grow with the number of hosts, even when they
+/// are scattered over many ranges.
+fn clone(c: &mut Criterion) {
+    let mut group = c.benchmark_group("clone");
+
+    for count in [100u64, 10_000, 100_000] {
+        let hostlist: Hostlist = (0..count).map(|i| format!("node{}", i * 2)).collect();
+
+        group.bench_with_input(BenchmarkId::from_parameter(count), &hostlist, |b, x| {
+            b.iter(|| black_box(x).clone())
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, clone);
+criterion_main!(benches);
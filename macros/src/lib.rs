@@ -0,0 +1,38 @@
+// Copyright (c) 2022 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, LitStr};
+
+/// Expands a hostlist expression at compile time into a `&'static [&'static str]` of its hosts, in
+/// the order [`hostlist_parser::parse`] gives them, so a fixed set needs no parsing at runtime.
+///
+/// An invalid expression fails the build with the parser's message and the byte offset of the
+/// error. Every host is written into the binary, so this is meant for small sets.
+///
+/// ```
+/// use hostlist_parser_macros::hostlist;
+///
+/// const OSS: &[&str] = hostlist!("oss[1-2].local,mds1");
+///
+/// assert_eq!(OSS, ["oss1.local", "oss2.local", "mds1"]);
+/// ```
+///
+/// ```compile_fail
+/// use hostlist_parser_macros::hostlist;
+///
+/// const OSS: &[&str] = hostlist!("oss[1-");
+/// ```
+#[proc_macro]
+pub fn hostlist(input: TokenStream) -> TokenStream {
+    let expr = parse_macro_input!(input as LitStr);
+
+    match hostlist_parser::parse(&expr.value()) {
+        Ok(hosts) => quote!(&[#(#hosts),*]).into(),
+        Err(err) => syn::Error::new(expr.span(), format!("Invalid hostlist: {err}"))
+            .to_compile_error()
+            .into(),
+    }
+}
@@ -0,0 +1,527 @@
+// Copyright (c) 2022 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+//! A stable, public view of a parsed expression.
+//!
+//! Each comma separated entry of an expression is a [`Hostlist`], made of literal text and bracket
+//! groups. Every type here can be written back out as an expression with `to_string`.
+
+use crate::{
+    parse_hostlists,
+    structures::{digit_bands, flatten_ranges, num_digits, Part, RangeOutput},
+    Error,
+};
+use itertools::Itertools as _;
+use std::fmt;
+
+/// One comma separated entry of an expression, such as `oss[1-4].local`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Hostlist {
+    pub exprs: Vec<Expr>,
+}
+
+/// A piece of a [`Hostlist`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Expr {
+    /// Text copied into every host as is.
+    Literal(String),
+    /// A bracket group; each host takes one value from it.
+    Bracket(Vec<Range>),
+    /// A bracket group of literal alternatives, as in `[web,db]`; each host takes one of them.
+    Words(Vec<String>),
+}
+
+/// A member of a bracket group.
+///
+/// `start` and `end` are in written order, so a reversed range has `start > end`. A single value
+/// has `start == end`. Values are zero-padded to at least `width` digits; a `width` of `0` means
+/// no padding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Range {
+    pub start: u64,
+    pub end: u64,
+    pub width: usize,
+    pub step: u64,
+    /// An alpha tag following a single value, as in `[1a,2b]`.
+    pub suffix: Option<String>,
+}
+
+impl Hostlist {
+    pub fn new(exprs: Vec<Expr>) -> Self {
+        Self { exprs }
+    }
+
+    pub(crate) fn from_parts(parts: &[Part]) -> Self {
+        let exprs = parts
+            .iter()
+            .map(|p| match p {
+                Part::String(x) => Expr::Literal(x.clone()),
+                Part::Range(xs) => match &xs[..] {
+                    [RangeOutput::Words(words)] => Expr::Words(words.clone()),
+                    _ => Expr::Bracket(xs.iter().flat_map(Range::from_output).collect()),
+                },
+            })
+            .collect();
+
+        Self { exprs }
+    }
+
+    pub(crate) fn to_parts(&self) -> Vec<Part> {
+        self.exprs
+            .iter()
+            .map(|x| match x {
+                Expr::Literal(x) => Part::String(x.clone()),
+                Expr::Bracket(xs) => Part::Range(xs.iter().map(Range::to_output).collect()),
+                Expr::Words(xs) => Part::Range(vec![RangeOutput::Words(xs.clone())]),
+            })
+            .collect()
+    }
+
+    /// Expands this entry into its hosts, without repeats.
+    pub fn expand(&self) -> Vec<String> {
+        expand(std::slice::from_ref(self))
+    }
+}
+
+impl Range {
+    pub fn new(start: u64, end: u64) -> Self {
+        Self {
+            start,
+            end,
+            width: 0,
+            step: 1,
+            suffix: None,
+        }
+    }
+
+    pub fn single(value: u64) -> Self {
+        Self::new(value, value)
+    }
+
+    pub fn with_width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub fn with_suffix(mut self, suffix: impl Into<String>) -> Self {
+        self.suffix = Some(suffix.into());
+        self
+    }
+
+    /// Takes every `step`th value from `start`, as in `[4-10:2]`.
+    pub fn with_step(mut self, step: u64) -> Self {
+        self.step = step;
+        self
+    }
+
+    fn from_output(x: &RangeOutput) -> Vec<Self> {
+        // A range with the same padding on both ends (`08-011`) keeps its leading zeros on every
+        // value, so its width grows with the number of digits; otherwise the width is fixed by the
+        // padded end (`08-10`).
+        let width =
+            |prefix: usize, same_prefix_len: bool, start: u64| match (prefix, same_prefix_len) {
+                (0, true) => 0,
+                (prefix, true) => prefix + num_digits(start),
+                (prefix, false) => prefix + 1,
+            };
+
+        match x {
+            RangeOutput::Range(prefix @ 1.., true, start, end) => digit_bands(*start, *end)
+                .map(|(digits, x)| Self::new(*x.start(), *x.end()).with_width(prefix + digits))
+                .collect(),
+            RangeOutput::RangeReversed(prefix @ 1.., true, end, start) => digit_bands(*end, *start)
+                .rev()
+                .map(|(digits, x)| Self::new(*x.end(), *x.start()).with_width(prefix + digits))
+                .collect(),
+            RangeOutput::Range(prefix, same_prefix_len, start, end) => {
+                vec![Self::new(*start, *end).with_width(width(*prefix, *same_prefix_len, *start))]
+            }
+            RangeOutput::RangeReversed(prefix, same_prefix_len, end, start) => {
+                vec![Self::new(*start, *end).with_width(width(*prefix, *same_prefix_len, *end))]
+            }
+            RangeOutput::Disjoint(xs) => xs
+                .iter()
+                .map(|(prefix, x, suffix)| Self {
+                    suffix: suffix.clone(),
+                    ..Self::single(*x).with_width(width(*prefix, true, *x))
+                })
+                .collect(),
+            // Each band of digits starts on the first value of the whole range plus a multiple of
+            // the step.
+            RangeOutput::Stepped(x, step) => {
+                let first = x.value_at(0).0;
+
+                Self::from_output(x)
+                    .into_iter()
+                    .filter_map(|mut x| {
+                        let gap = first.abs_diff(x.start) % step;
+                        let skip = (step - gap) % step;
+
+                        if x.start <= x.end {
+                            x.start = x.start.checked_add(skip).filter(|&v| v <= x.end)?;
+                        } else {
+                            x.start = x.start.checked_sub(skip).filter(|&v| v >= x.end)?;
+                        }
+
+                        Some(x.with_step(*step))
+                    })
+                    .collect()
+            }
+            RangeOutput::Words(_) => unreachable!("words fill a bracket group on their own"),
+        }
+    }
+
+    fn to_output(&self) -> RangeOutput {
+        // Padding to a fixed width of at least two digits keeps leading zeros; a width of one is
+        // the same as none.
+        let (prefix, same_prefix_len) = match self.width {
+            0 | 1 => (0, true),
+            width => (width - 1, false),
+        };
+
+        if self.start == self.end || self.suffix.is_some() {
+            let values: Box<dyn Iterator<Item = u64>> = if self.start <= self.end {
+                Box::new(self.start..=self.end)
+            } else {
+                Box::new((self.end..=self.start).rev())
+            };
+
+            return RangeOutput::Disjoint(
+                values
+                    .step_by(self.step.max(1) as usize)
+                    .map(|x| {
+                        (
+                            self.width.saturating_sub(num_digits(x)),
+                            x,
+                            self.suffix.clone(),
+                        )
+                    })
+                    .collect(),
+            );
+        }
+
+        let range = if self.start < self.end {
+            RangeOutput::Range(prefix, same_prefix_len, self.start, self.end)
+        } else {
+            RangeOutput::RangeReversed(prefix, same_prefix_len, self.end, self.start)
+        };
+
+        match self.step {
+            0 | 1 => range,
+            step => RangeOutput::Stepped(Box::new(range), step),
+        }
+    }
+}
+
+impl fmt::Display for Hostlist {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for x in &self.exprs {
+            write!(f, "{x}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Literal(x)
+                if x.chars()
+                    .all(|c| c.is_alphanumeric() || c == '-' || c == '.') =>
+            {
+                write!(f, "{x}")
+            }
+            Expr::Literal(x) => {
+                write!(f, "\"")?;
+
+                for c in x.chars() {
+                    if c == '"' || c == '\\' {
+                        write!(f, "\\")?;
+                    }
+
+                    write!(f, "{c}")?;
+                }
+
+                write!(f, "\"")
+            }
+            Expr::Bracket(xs) => {
+                write!(f, "[")?;
+
+                for (i, x) in xs.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+
+                    write!(f, "{x}")?;
+                }
+
+                write!(f, "]")
+            }
+            Expr::Words(xs) => write!(f, "[{}]", xs.join(",")),
+        }
+    }
+}
+
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self.width;
+
+        write!(f, "{:0>width$}", self.start)?;
+
+        if self.start != self.end {
+            write!(f, "-{:0>width$}", self.end)?;
+        }
+
+        if self.step != 1 {
+            write!(f, ":{}", self.step)?;
+        }
+
+        if let Some(suffix) = &self.suffix {
+            write!(f, "{suffix}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses `input` into its structure, one [`Hostlist`] per comma separated entry, without
+/// expanding anything.
+///
+/// ```
+/// use hostlist_parser::parse_ast;
+///
+/// let xs = parse_ast("oss[01-04].local, mds1").unwrap();
+///
+/// assert_eq!(xs.len(), 2);
+/// assert_eq!(xs[0].to_string(), "oss[01-04].local");
+/// ```
+pub fn parse_ast(input: &str) -> Result<Vec<Hostlist>, Error> {
+    let hosts = parse_hostlists(input)?;
+
+    Ok(hosts
+        .iter()
+        .map(|parts| Hostlist::from_parts(parts))
+        .collect())
+}
+
+/// Expands `hostlists` into their hosts, in order, dropping any host already seen.
+///
+/// ```
+/// use hostlist_parser::ast::{self, Expr, Range};
+///
+/// let mut xs = ast::parse_ast("oss[1-4].local, mds1").unwrap();
+///
+/// xs[0].exprs[1] = Expr::Bracket(vec![Range::single(2), Range::new(7, 8)]);
+///
+/// assert_eq!(
+///     ast::expand(&xs),
+///     vec!["oss2.local", "oss7.local", "oss8.local", "mds1"]
+/// );
+/// ```
+pub fn expand(hostlists: &[Hostlist]) -> Vec<String> {
+    let mut xs = vec![];
+
+    for hostlist in hostlists {
+        let parts = hostlist.to_parts();
+        let x_prod: Vec<_> = parts
+            .iter()
+            .filter_map(Part::get_ranges)
+            .map(|xs| flatten_ranges(xs))
+            .multi_cartesian_product()
+            .collect();
+
+        // No ranges means no interpolation
+        if x_prod.is_empty() {
+            let mut s = String::new();
+
+            for p in parts.clone() {
+                if let Part::String(x) = p {
+                    s.push_str(&x)
+                }
+            }
+
+            xs.push(s);
+        } else {
+            for ys in x_prod {
+                let mut it = ys.iter();
+
+                let mut s = String::new();
+
+                for p in parts.clone() {
+                    match p {
+                        Part::String(x) => s.push_str(&x),
+                        Part::Range(_) => s.push_str(it.next().unwrap()),
+                    }
+                }
+
+                xs.push(s);
+            }
+        }
+    }
+
+    xs.into_iter().unique().collect()
+}
+
+/// Proptest strategies producing valid expressions, for round-trip tests across the crate.
+#[cfg(test)]
+pub(crate) mod arbitrary {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// A bracket member: a range in either order, a single value with an optional alpha suffix,
+    /// or a stepped range, each either natural or zero-padded.
+    fn range() -> impl Strategy<Value = Range> {
+        let width = prop_oneof![Just(0), 2..5usize];
+
+        prop_oneof![
+            (0..300u64, 0..12u64, any::<bool>(), width.clone()).prop_map(
+                |(start, len, reversed, width)| {
+                    let x = if reversed {
+                        Range::new(start + len, start)
+                    } else {
+                        Range::new(start, start + len)
+                    };
+
+                    x.with_width(width)
+                }
+            ),
+            (0..300u64, proptest::option::of("[a-z]{1,2}"), width.clone()).prop_map(
+                |(x, suffix, width)| Range {
+                    suffix,
+                    ..Range::single(x).with_width(width)
+                }
+            ),
+            (0..300u64, 2..20u64, 2..5u64, width).prop_map(|(start, len, step, width)| Range::new(
+                start,
+                start + len
+            )
+            .with_width(width)
+            .with_step(step)),
+        ]
+    }
+
+    /// An entry starting with literal text, followed by up to two bracket groups.
+    fn hostlist() -> impl Strategy<Value = Hostlist> {
+        (
+            "[a-z]{1,3}",
+            prop::collection::vec(
+                (
+                    prop::collection::vec(range(), 1..4),
+                    prop::option::of("[a-z.-]{1,3}"),
+                ),
+                0..3,
+            ),
+        )
+            .prop_map(|(head, groups)| {
+                let mut exprs = vec![Expr::Literal(head)];
+
+                for (ranges, literal) in groups {
+                    exprs.push(Expr::Bracket(ranges));
+                    exprs.extend(literal.map(Expr::Literal));
+                }
+
+                Hostlist::new(exprs)
+            })
+    }
+
+    /// A whole expression, as its comma separated entries.
+    pub(crate) fn hostlists() -> impl Strategy<Value = Vec<Hostlist>> {
+        prop::collection::vec(hostlist(), 1..4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use insta::assert_debug_snapshot;
+
+    #[test]
+    fn test_parse_ast() {
+        assert_debug_snapshot!(parse_ast("oss[01-04,7].local, mds1"));
+        assert_debug_snapshot!(parse_ast("node[7-5,009,1a]"));
+    }
+
+    #[test]
+    fn test_parse_ast_step() {
+        let xs = parse_ast("n[1,4-10:2,015-009:3]").unwrap();
+
+        assert_eq!(xs[0].to_string(), "n[1,4-10:2,015-009:3]");
+        assert_eq!(
+            xs[0].expand(),
+            vec!["n1", "n4", "n6", "n8", "n10", "n015", "n012", "n009"]
+        );
+
+        let xs = parse_ast("n[08-012:3],n[011-08:2]").unwrap();
+
+        assert_eq!(xs[0].to_string(), "n[08-09:3,011-012:3]");
+        assert_eq!(xs[0].expand(), vec!["n08", "n011"]);
+        assert_eq!(xs[1].expand(), vec!["n011", "n09"]);
+        assert_eq!(xs[0].expand(), parse("n[08-012:3]").unwrap());
+    }
+
+    #[test]
+    fn test_to_string() {
+        let x = Hostlist::new(vec![
+            Expr::Literal("weird \"name\"".into()),
+            Expr::Bracket(vec![
+                Range::new(1, 10).with_width(3),
+                Range::single(12).with_suffix("b"),
+                Range::new(9, 7),
+            ]),
+        ]);
+
+        assert_eq!(x.to_string(), r#""weird \"name\""[001-010,12b,9-7]"#);
+    }
+
+    #[test]
+    fn test_expand() {
+        let x = Hostlist::new(vec![
+            Expr::Literal("n".into()),
+            Expr::Bracket(vec![
+                Range::new(9, 11).with_width(3),
+                Range::new(3, 1),
+                Range::single(5).with_width(2).with_suffix("a"),
+                Range::new(1, 2),
+            ]),
+        ]);
+
+        assert_debug_snapshot!(x.expand());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for input in [
+            "oss[1,2].local",
+            "hostname[10,11-12,002-003,5].iml.com",
+            "hostname[7-5], hostname[8,9], hostname[3,2,1]",
+            "hostname[001-999]",
+            "hostname[1,2]-[3-4]-[5,6].iml.com",
+            "hostname[1,2][3,4].iml.com",
+            "hostname[06-10],test[000-002].localdomain",
+            "hostname[01-100],hostname[010-099]",
+            "hostname4.iml.com,hostname4.iml.com",
+            "OST01[00,01]",
+            "slot[01a,2,3-4,5bc].local",
+            r#"node[1-2],"weird[name]", "x,y"[1-2]"#,
+        ] {
+            let expr = parse_ast(input)
+                .unwrap()
+                .iter()
+                .map(Hostlist::to_string)
+                .collect::<Vec<_>>()
+                .join(",");
+
+            assert_eq!(
+                parse(&expr).unwrap(),
+                parse(input).unwrap(),
+                "{input} -> {expr}"
+            );
+        }
+    }
+}
@@ -0,0 +1,217 @@
+// Copyright (c) 2022 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+use crate::{
+    ast::{self, Expr},
+    hostlist::check_expansion,
+    structures::MAX_WIDTH,
+    Error, Hostlist,
+};
+use std::slice;
+
+/// Builds an expression piece by piece, without formatting it by hand.
+///
+/// Literal text and bracket groups are appended in order, and [`HostlistBuilder::pad`] sets the
+/// zero padding of the last bracket group. Mistakes are reported by [`HostlistBuilder::build`] and
+/// [`HostlistBuilder::to_expression`] as [`Error::InvalidGroup`].
+///
+/// ```
+/// use hostlist_parser::HostlistBuilder;
+///
+/// let builder = HostlistBuilder::new()
+///     .literal("oss")
+///     .range(1, 24)
+///     .pad(2)
+///     .literal(".local");
+///
+/// assert_eq!(builder.to_expression().unwrap(), "oss[01-24].local");
+/// assert_eq!(builder.build().unwrap().len(), 24);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct HostlistBuilder {
+    exprs: Vec<Expr>,
+    groups: usize,
+    /// The first invalid bracket group, and why.
+    error: Option<(usize, &'static str)>,
+}
+
+impl HostlistBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends text copied into every host as is.
+    pub fn literal(mut self, literal: impl Into<String>) -> Self {
+        self.exprs.push(Expr::Literal(literal.into()));
+        self
+    }
+
+    /// Appends a bracket group counting up from `start` to `end`, inclusive. `start` must not be
+    /// after `end`; see [`HostlistBuilder::reversed`] to count down.
+    pub fn range(self, start: u64, end: u64) -> Self {
+        self.group(
+            vec![ast::Range::new(start, end)],
+            (start > end).then_some("start is after end, use `reversed` to count down"),
+        )
+    }
+
+    /// Appends a bracket group counting down from `start` to `end`, inclusive. `start` must not be
+    /// before `end`.
+    pub fn reversed(self, start: u64, end: u64) -> Self {
+        self.group(
+            vec![ast::Range::new(start, end)],
+            (start < end).then_some("start is before end, use `range` to count up"),
+        )
+    }
+
+    /// Appends a bracket group holding each of `values`, in order, as in `[1,3,7]`.
+    pub fn values(self, values: impl IntoIterator<Item = u64>) -> Self {
+        let ranges: Vec<_> = values.into_iter().map(ast::Range::single).collect();
+        let error = ranges.is_empty().then_some("no values");
+
+        self.group(ranges, error)
+    }
+
+    /// Zero-pads the values of the last bracket group to `width` digits. A `width` of `0` or `1`
+    /// means no padding.
+    pub fn pad(mut self, width: usize) -> Self {
+        let group = self.groups.saturating_sub(1);
+        let last = self.exprs.iter_mut().rev().find_map(|x| match x {
+            Expr::Bracket(xs) => Some(xs),
+            Expr::Literal(_) | Expr::Words(_) => None,
+        });
+
+        let error = match last {
+            None => Some("padding set before any bracket group"),
+            Some(_) if width > MAX_WIDTH => Some("padding is wider than 20 digits"),
+            Some(xs) => {
+                for x in xs {
+                    x.width = width;
+                }
+
+                None
+            }
+        };
+
+        self.fail(group, error)
+    }
+
+    /// The set of hosts the expression holds.
+    pub fn build(&self) -> Result<Hostlist, Error> {
+        let parts = self.to_ast()?.to_parts();
+
+        check_expansion(slice::from_ref(&parts))?;
+
+        Ok(Hostlist::from_parts(&parts))
+    }
+
+    /// The expression, as it would be written by hand.
+    pub fn to_expression(&self) -> Result<String, Error> {
+        Ok(self.to_ast()?.to_string())
+    }
+
+    fn to_ast(&self) -> Result<ast::Hostlist, Error> {
+        match self.error {
+            Some((group, reason)) => Err(Error::InvalidGroup { group, reason }),
+            None => Ok(ast::Hostlist::new(self.exprs.clone())),
+        }
+    }
+
+    fn group(mut self, ranges: Vec<ast::Range>, error: Option<&'static str>) -> Self {
+        self.exprs.push(Expr::Bracket(ranges));
+        self.groups += 1;
+
+        let group = self.groups - 1;
+
+        self.fail(group, error)
+    }
+
+    /// Keeps the first error, so it is the one reported.
+    fn fail(mut self, group: usize, reason: Option<&'static str>) -> Self {
+        if let Some(reason) = reason {
+            self.error.get_or_insert((group, reason));
+        }
+
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_builder() {
+        let x = HostlistBuilder::new()
+            .literal("r")
+            .range(1, 2)
+            .literal("n")
+            .values([3, 1, 10])
+            .pad(2)
+            .literal("-ib");
+
+        assert_eq!(x.to_expression().unwrap(), "r[1-2]n[03,01,10]-ib");
+        assert_eq!(
+            x.build().unwrap(),
+            Hostlist::parse("r[1-2]n[01,03,10]-ib").unwrap()
+        );
+        assert_eq!(
+            HostlistBuilder::new()
+                .literal("node")
+                .reversed(10, 8)
+                .pad(3)
+                .to_expression()
+                .unwrap(),
+            "node[010-008]"
+        );
+        assert_eq!(
+            parse(
+                &HostlistBuilder::new()
+                    .literal("weird,name")
+                    .range(1, 2)
+                    .to_expression()
+                    .unwrap()
+            )
+            .unwrap(),
+            vec!["weird,name1", "weird,name2"]
+        );
+    }
+
+    #[test]
+    fn test_builder_errors() {
+        let invalid = |group, reason| Err::<String, _>(Error::InvalidGroup { group, reason });
+
+        assert_eq!(
+            HostlistBuilder::new()
+                .literal("n")
+                .range(5, 1)
+                .to_expression(),
+            invalid(0, "start is after end, use `reversed` to count down")
+        );
+        assert_eq!(
+            HostlistBuilder::new()
+                .range(1, 2)
+                .reversed(1, 5)
+                .to_expression(),
+            invalid(1, "start is before end, use `range` to count up")
+        );
+        assert!(matches!(
+            HostlistBuilder::new().range(2, 1).build(),
+            Err(Error::InvalidGroup { group: 0, .. })
+        ));
+        assert_eq!(
+            HostlistBuilder::new().values([]).to_expression(),
+            invalid(0, "no values")
+        );
+        assert_eq!(
+            HostlistBuilder::new().literal("n").pad(2).to_expression(),
+            invalid(0, "padding set before any bracket group")
+        );
+        assert_eq!(
+            HostlistBuilder::new().range(1, 2).pad(21).to_expression(),
+            invalid(0, "padding is wider than 20 digits")
+        );
+    }
+}
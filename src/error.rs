@@ -0,0 +1,98 @@
+// Copyright (c) 2022 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+use crate::ParseErrors;
+use std::fmt;
+
+/// An owned error returned by the checked parsing functions.
+#[derive(Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The expression is not a valid hostlist.
+    Parse(combine::stream::easy::Errors<char, String, usize>),
+    /// The expression expands to more hosts than the caller allowed.
+    TooManyHosts { count: u128, limit: usize },
+    /// A single bracket group holds more values than the caller allowed.
+    RangeTooWide {
+        range: String,
+        width: u128,
+        limit: usize,
+    },
+    /// The expression produces the same host more than once.
+    DuplicateHost { host: String },
+    /// A single host name was expected, but an expression was given.
+    NotAHost { host: String },
+    /// The expression is longer than the caller allowed.
+    InputTooLong { len: usize, limit: usize },
+    /// The input bytes are not valid UTF-8, starting at byte `offset`.
+    InvalidUtf8 { offset: usize },
+    /// A bracket group given to a [`crate::HostlistBuilder`] is invalid. `group` counts bracket
+    /// groups from 0.
+    InvalidGroup { group: usize, reason: &'static str },
+    /// A template does not fit what it is filled with.
+    InvalidTemplate { reason: String },
+    /// A range or step separator given to [`crate::ParseOptions`] cannot be used.
+    InvalidSeparator { sep: char, reason: &'static str },
+    /// A pattern to filter hosts with is malformed.
+    InvalidPattern { reason: String },
+    /// A group referenced as `@group` could not be resolved.
+    UnknownGroup { group: String },
+    /// Encoded bytes use a version of the encoding this crate does not know.
+    UnsupportedVersion { version: u8 },
+    /// Encoded bytes are truncated or malformed, starting at byte `offset`.
+    InvalidEncoding { offset: usize },
+    /// Reading the expression from its source failed.
+    Io { message: String },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Parse(err) => write!(f, "{err}"),
+            Error::TooManyHosts { count, limit } => write!(
+                f,
+                "Expression expands to {count} hosts, which exceeds the limit of {limit}"
+            ),
+            Error::RangeTooWide {
+                range,
+                width,
+                limit,
+            } => write!(
+                f,
+                "Range {range} holds {width} values, which exceeds the limit of {limit}"
+            ),
+            Error::DuplicateHost { host } => write!(f, "Host {host} appears more than once"),
+            Error::NotAHost { host } => write!(f, "{host:?} is not a single host name"),
+            Error::InputTooLong { len, limit } => write!(
+                f,
+                "Expression is {len} bytes long, which exceeds the limit of {limit}"
+            ),
+            Error::InvalidUtf8 { offset } => {
+                write!(f, "Expression is not valid UTF-8 at byte {offset}")
+            }
+            Error::InvalidGroup { group, reason } => {
+                write!(f, "Bracket group {group} is invalid: {reason}")
+            }
+            Error::InvalidTemplate { reason } => write!(f, "Invalid template: {reason}"),
+            Error::InvalidSeparator { sep, reason } => {
+                write!(f, "{sep:?} cannot be used as a separator: {reason}")
+            }
+            Error::InvalidPattern { reason } => write!(f, "Invalid pattern: {reason}"),
+            Error::UnknownGroup { group } => write!(f, "Group @{group} could not be resolved"),
+            Error::UnsupportedVersion { version } => {
+                write!(f, "Encoding version {version} is not supported")
+            }
+            Error::InvalidEncoding { offset } => write!(f, "Invalid encoding at byte {offset}"),
+            Error::Io { message } => write!(f, "Failed to read expression: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<ParseErrors<'_>> for Error {
+    fn from(err: ParseErrors<'_>) -> Self {
+        Error::Parse(err.map_range(|r| r.to_string()))
+    }
+}
@@ -0,0 +1,364 @@
+// Copyright (c) 2022 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+use crate::{parse_hostlists, structures::Part, Error, Expansion};
+#[cfg(feature = "regex")]
+use regex::Regex;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Char(char),
+    /// `?`, any single character.
+    Any,
+    /// `*`, any run of characters.
+    Star,
+    /// `[...]`, a character in (or, negated, outside) the inclusive ranges.
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl Token {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Token::Char(x) => *x == c,
+            Token::Any => true,
+            Token::Star => unreachable!("a star matches runs, not characters"),
+            Token::Class { negated, ranges } => {
+                ranges.iter().any(|(lo, hi)| (*lo..=*hi).contains(&c)) != *negated
+            }
+        }
+    }
+}
+
+/// A shell-style pattern matched against whole host names.
+#[derive(Debug, Clone)]
+struct Glob(Vec<Token>);
+
+impl Glob {
+    fn new(pattern: &str) -> Result<Self, Error> {
+        let invalid = |reason: String| Error::InvalidPattern { reason };
+        let mut tokens = vec![];
+        let mut chars = pattern.char_indices().peekable();
+
+        while let Some((i, c)) = chars.next() {
+            let token = match c {
+                '?' => Token::Any,
+                '*' => Token::Star,
+                '[' => {
+                    let negated = chars.next_if(|(_, c)| matches!(c, '!' | '^')).is_some();
+                    let mut ranges = vec![];
+
+                    // A `]` right after the opening bracket is a member, not the end.
+                    while let Some((_, lo)) = chars.next_if(|(_, c)| *c != ']' || ranges.is_empty())
+                    {
+                        let hi = match chars.peek() {
+                            Some((_, '-')) => {
+                                chars.next();
+
+                                match chars.next_if(|(_, c)| *c != ']') {
+                                    Some((_, hi)) => hi,
+                                    // A trailing `-` is a member, as in `[a-]`.
+                                    None => {
+                                        ranges.push(('-', '-'));
+
+                                        lo
+                                    }
+                                }
+                            }
+                            _ => lo,
+                        };
+
+                        if lo > hi {
+                            return Err(invalid(format!(
+                                "reversed range `{lo}-{hi}` in the class at byte {i}"
+                            )));
+                        }
+
+                        ranges.push((lo, hi));
+                    }
+
+                    if chars.next().is_none() {
+                        return Err(invalid(format!("unclosed `[` at byte {i}")));
+                    }
+
+                    Token::Class { negated, ranges }
+                }
+                c => Token::Char(c),
+            };
+
+            tokens.push(token);
+        }
+
+        Ok(Self(tokens))
+    }
+
+    /// Whether the whole of `s` matches. A star is first tried empty and grown on a mismatch,
+    /// resuming from the latest star only, which is enough as stars match any run.
+    fn matches(&self, s: &str) -> bool {
+        let s: Vec<char> = s.chars().collect();
+        let tokens = &self.0;
+        let (mut t, mut i) = (0, 0);
+        let mut star = None;
+
+        while i < s.len() {
+            match tokens.get(t) {
+                Some(Token::Star) => {
+                    star = Some((t, i));
+                    t += 1;
+                }
+                Some(x) if x.matches(s[i]) => {
+                    t += 1;
+                    i += 1;
+                }
+                _ => match star {
+                    Some((st, si)) => {
+                        star = Some((st, si + 1));
+                        t = st + 1;
+                        i = si + 1;
+                    }
+                    None => return false,
+                },
+            }
+        }
+
+        tokens[t..].iter().all(|x| *x == Token::Star)
+    }
+
+    /// The text every match starts with.
+    fn prefix(&self) -> String {
+        self.0
+            .iter()
+            .map_while(|x| match x {
+                Token::Char(c) => Some(c),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The text every match ends with.
+    fn suffix(&self) -> String {
+        let mut xs: Vec<_> = self
+            .0
+            .iter()
+            .rev()
+            .map_while(|x| match x {
+                Token::Char(c) => Some(*c),
+                _ => None,
+            })
+            .collect();
+
+        xs.reverse();
+        xs.into_iter().collect()
+    }
+}
+
+/// Whether `a` and `b` agree on their common length, so one may extend the other.
+fn agree(a: impl Iterator<Item = char>, b: impl Iterator<Item = char>) -> bool {
+    a.zip(b).all(|(x, y)| x == y)
+}
+
+/// The literal text an entry starts with, and the text it ends with, before and after its first
+/// and last bracket groups. An entry without brackets is the same text twice.
+fn literal_ends(parts: &[Part]) -> (String, String) {
+    let text = |xs: &mut dyn Iterator<Item = &Part>| -> Vec<String> {
+        xs.map_while(|x| match x {
+            Part::String(x) => Some(x.clone()),
+            Part::Range(_) => None,
+        })
+        .collect()
+    };
+
+    let head = text(&mut parts.iter()).concat();
+    let mut tail = text(&mut parts.iter().rev());
+
+    tail.reverse();
+
+    (head, tail.concat())
+}
+
+/// Expands `expr` like [`crate::parse`], keeping only the hosts matching the shell-style
+/// `pattern`: `*` matches any run of characters, `?` any single one, and `[...]` one of a class,
+/// such as `[0-9]`, or one outside it with `[!...]`. Patterns match whole host names.
+///
+/// Entries whose literal text cannot match the text at the start or end of `pattern` are skipped
+/// without being expanded, so `gpu*` only expands the entries that may start with `gpu`. Other
+/// entries are expanded a host at a time, keeping only the matches.
+///
+/// ```
+/// use hostlist_parser::parse_filtered;
+///
+/// assert_eq!(
+///     parse_filtered("rack[1-2]-gpu-[1-2],rack[1-2]-cpu-[1-4]", "*-gpu-*").unwrap(),
+///     vec!["rack1-gpu-1", "rack1-gpu-2", "rack2-gpu-1", "rack2-gpu-2"]
+/// );
+/// assert_eq!(
+///     parse_filtered("node[1-18446744073709551615],mds[1-4]", "mds[!3]").unwrap(),
+///     vec!["mds1", "mds2", "mds4"]
+/// );
+/// ```
+pub fn parse_filtered(expr: &str, pattern: &str) -> Result<Vec<String>, Error> {
+    let glob = Glob::new(pattern)?;
+    let (prefix, suffix) = (glob.prefix(), glob.suffix());
+
+    filter_hosts(
+        expr,
+        |parts| {
+            let (head, tail) = literal_ends(parts);
+
+            agree(head.chars(), prefix.chars()) && agree(tail.chars().rev(), suffix.chars().rev())
+        },
+        |host| glob.matches(host),
+    )
+}
+
+/// Expands `expr` like [`crate::parse`], keeping only the hosts `regex` finds a match in. Anchor
+/// the regex with `^` and `$` to match whole host names.
+///
+/// Hosts are expanded and checked one at a time, so only the matches are held.
+///
+/// ```
+/// use hostlist_parser::parse_filtered_regex;
+/// use regex::Regex;
+///
+/// let even = Regex::new(r"^oss\d*[02468]\.local$").unwrap();
+///
+/// assert_eq!(
+///     parse_filtered_regex("oss[1-4].local,mds[1-2]", &even).unwrap(),
+///     vec!["oss2.local", "oss4.local"]
+/// );
+/// ```
+#[cfg(feature = "regex")]
+pub fn parse_filtered_regex(expr: &str, regex: &Regex) -> Result<Vec<String>, Error> {
+    filter_hosts(expr, |_| true, |host| regex.is_match(host))
+}
+
+/// Expands the entries of `expr` that `entry` accepts, a host at a time, keeping the first of each
+/// host that `host` accepts.
+fn filter_hosts(
+    expr: &str,
+    entry: impl Fn(&[Part]) -> bool,
+    host: impl Fn(&str) -> bool,
+) -> Result<Vec<String>, Error> {
+    let mut seen = HashSet::new();
+    let mut xs = vec![];
+
+    for parts in parse_hostlists(expr)? {
+        if !entry(&parts) {
+            continue;
+        }
+
+        for x in Expansion::new(vec![parts]) {
+            if host(&x) && seen.insert(x.clone()) {
+                xs.push(x);
+            }
+        }
+    }
+
+    Ok(xs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use insta::assert_debug_snapshot;
+
+    #[test]
+    fn test_glob_matches() {
+        let glob = |x| Glob::new(x).unwrap();
+
+        assert!(glob("node*").matches("node12"));
+        assert!(glob("*").matches(""));
+        assert!(glob("n?de[0-9]").matches("node7"));
+        assert!(!glob("n?de[0-9]").matches("node"));
+        assert!(glob("*-gpu-*").matches("r1-gpu-2"));
+        assert!(!glob("*-gpu-*").matches("r1-gpu"));
+        assert!(glob("a*b*c").matches("aXbYbZc"));
+        assert!(!glob("a*b*c").matches("aXbYc-"));
+        assert!(glob("[]a]").matches("]"));
+        assert!(glob("[a-]").matches("-"));
+        assert!(glob("[!a-c]x").matches("dx"));
+        assert!(!glob("[^a-c]x").matches("bx"));
+        assert!(glob("é?").matches("éa"));
+    }
+
+    #[test]
+    fn test_glob_ends() {
+        let glob = Glob::new("oss*.local").unwrap();
+
+        assert_eq!(
+            (glob.prefix(), glob.suffix()),
+            ("oss".into(), ".local".into())
+        );
+        assert_eq!(Glob::new("*").unwrap().prefix(), "");
+    }
+
+    #[test]
+    fn test_glob_errors() {
+        assert_debug_snapshot!(Glob::new("node[0-9"));
+        assert_debug_snapshot!(Glob::new("node[9-0]"));
+    }
+
+    #[test]
+    fn test_parse_filtered() {
+        // Pushed down: the huge entry is skipped as its prefix cannot match.
+        assert_eq!(
+            parse_filtered("node[0-18446744073709551615],gpu[1-2],gpu1", "gpu*").unwrap(),
+            vec!["gpu1", "gpu2"]
+        );
+        assert_eq!(
+            parse_filtered("node[0-18446744073709551615].ib,oss[1-3].local", "*.local").unwrap(),
+            vec!["oss1.local", "oss2.local", "oss3.local"]
+        );
+
+        // Not pushed down: every host of the entry is checked.
+        assert_eq!(
+            parse_filtered("n[1-20]", "n?").unwrap(),
+            vec!["n1", "n2", "n3", "n4", "n5", "n6", "n7", "n8", "n9"]
+        );
+        assert_eq!(
+            parse_filtered("[web,db][1-2]", "*b1").unwrap(),
+            vec!["web1", "db1"]
+        );
+        assert_eq!(
+            parse_filtered("n[1-3]", "m*").unwrap(),
+            Vec::<String>::new()
+        );
+
+        assert!(matches!(parse_filtered("n[1-", "*"), Err(Error::Parse(_))));
+        assert!(matches!(
+            parse_filtered("n1", "n["),
+            Err(Error::InvalidPattern { .. })
+        ));
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_parse_filtered_regex() {
+        let regex = |x| Regex::new(x).unwrap();
+
+        assert_eq!(
+            parse_filtered_regex("rack[1-2]-[gpu,cpu]-[1-2]", &regex(r"^rack2-gpu-\d+$")).unwrap(),
+            vec!["rack2-gpu-1", "rack2-gpu-2"]
+        );
+        assert_eq!(
+            parse_filtered_regex(
+                "r[1-2]-gpu-[1-3],r[1-2]-cpu-[1-3]",
+                &regex(r"^r2-(gpu|cpu)-[13]$")
+            )
+            .unwrap(),
+            vec!["r2-gpu-1", "r2-gpu-3", "r2-cpu-1", "r2-cpu-3"]
+        );
+        assert_eq!(
+            parse_filtered_regex("n[1-3],n[2-4]", &regex("[34]")).unwrap(),
+            vec!["n3", "n4"]
+        );
+        assert!(matches!(
+            parse_filtered_regex("n[1-", &regex(".")),
+            Err(Error::Parse(_))
+        ));
+    }
+}
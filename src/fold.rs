@@ -0,0 +1,276 @@
+// Copyright (c) 2022 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+use crate::{
+    ast::{self, Expr},
+    range_set::RangeSet,
+    structures::num_digits,
+};
+use itertools::Itertools as _;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The text around every run of digits of a host, and the zero padding of each run.
+///
+/// There is always one more literal than there are runs; the first and last may be empty.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Template {
+    literals: Vec<String>,
+    widths: Vec<usize>,
+}
+
+impl Template {
+    /// Splits `host` at each of its runs of digits. Returns `None` if there are none, or if one does
+    /// not fit in a `u64`.
+    fn split(host: &str) -> Option<(Self, Vec<u64>)> {
+        let mut literals = vec![];
+        let mut widths = vec![];
+        let mut values = vec![];
+        let mut rest = host;
+
+        loop {
+            let start = rest
+                .find(|c: char| c.is_ascii_digit())
+                .unwrap_or(rest.len());
+
+            literals.push(rest[..start].to_string());
+            rest = &rest[start..];
+
+            if rest.is_empty() {
+                break;
+            }
+
+            let end = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let digits = &rest[..end];
+            let value = digits.parse::<u64>().ok()?;
+
+            widths.push(if digits.len() > num_digits(value) {
+                digits.len()
+            } else {
+                0
+            });
+            values.push(value);
+            rest = &rest[end..];
+        }
+
+        (!values.is_empty()).then_some((Template { literals, widths }, values))
+    }
+
+    fn to_ast(&self, sets: &[RangeSet]) -> ast::Hostlist {
+        let mut exprs = vec![];
+        let mut literal = self.literals[0].clone();
+
+        for ((set, width), next) in sets.iter().zip(&self.widths).zip(&self.literals[1..]) {
+            let ranges: Vec<_> = set
+                .iter()
+                .map(|(lo, hi)| ast::Range::new(lo, hi).with_width(*width))
+                .collect();
+
+            match &ranges[..] {
+                [x] if x.start == x.end => {
+                    literal.push_str(&format!("{:0>width$}", x.start, width = *width))
+                }
+                _ => {
+                    if !literal.is_empty() {
+                        exprs.push(Expr::Literal(std::mem::take(&mut literal)));
+                    }
+
+                    exprs.push(Expr::Bracket(ranges));
+                }
+            }
+
+            literal.push_str(next);
+        }
+
+        if !literal.is_empty() {
+            exprs.push(Expr::Literal(literal));
+        }
+
+        ast::Hostlist::new(exprs)
+    }
+}
+
+/// Moves each host into the template with the most padded runs that also writes it, so `n10` joins
+/// `n09` in `n[09-10]`: a number that fills a padded width is written the same without padding.
+/// This lets padded ranges fold across a power of ten.
+fn merge_padding(
+    groups: BTreeMap<Template, BTreeSet<Vec<u64>>>,
+) -> BTreeMap<Template, BTreeSet<Vec<u64>>> {
+    let mut by_literals: BTreeMap<Vec<String>, Vec<Template>> = BTreeMap::new();
+
+    for template in groups.keys() {
+        by_literals
+            .entry(template.literals.clone())
+            .or_default()
+            .push(template.clone());
+    }
+
+    let mut merged: BTreeMap<Template, BTreeSet<Vec<u64>>> = BTreeMap::new();
+
+    for (template, points) in groups {
+        let candidates = &by_literals[&template.literals];
+
+        for values in points {
+            let target = candidates
+                .iter()
+                .filter(|x| {
+                    x.widths.iter().zip(&template.widths).zip(&values).all(
+                        |((&to, &from), &value)| {
+                            to == from || (from == 0 && to == num_digits(value))
+                        },
+                    )
+                })
+                .max_by_key(|x| x.widths.iter().filter(|&&width| width > 0).count())
+                .unwrap_or(&template);
+
+            merged.entry(target.clone()).or_default().insert(values);
+        }
+    }
+
+    merged
+}
+
+/// Folds each dimension of `points` in turn, innermost first. Points are only merged along a
+/// dimension when they agree on every other one, so each entry returned is a complete cross
+/// product of its sets.
+fn fold_points(points: BTreeSet<Vec<u64>>) -> Vec<Vec<RangeSet>> {
+    let dims = points.first().map_or(0, Vec::len);
+
+    let mut entries: Vec<Vec<RangeSet>> = points
+        .into_iter()
+        .map(|xs| xs.into_iter().map(RangeSet::single).collect())
+        .collect();
+
+    for d in (0..dims).rev() {
+        let mut merged: BTreeMap<Vec<RangeSet>, RangeSet> = BTreeMap::new();
+
+        for mut entry in entries {
+            let set = entry.remove(d);
+            let union = merged.entry(entry).or_default();
+
+            for (lo, hi) in set.iter() {
+                union.insert(lo, hi);
+            }
+        }
+
+        entries = merged
+            .into_iter()
+            .map(|(mut entry, set)| {
+                entry.insert(d, set);
+                entry
+            })
+            .collect();
+    }
+
+    entries.sort();
+
+    entries
+}
+
+/// Folds a list of host names into an expression, like [`crate::fold`], but across every run of
+/// digits in a name rather than only the last one.
+///
+/// Names sharing the same text around their numbers, and the same padding for each, are folded
+/// together. Several bracket groups are only written when every combination of their values is
+/// present; otherwise the names are split into entries that each are such a complete product,
+/// folding the innermost numbers first. Parsing the output gives back exactly the input names.
+///
+/// ```
+/// use hostlist_parser::fold_nd;
+///
+/// let hosts = hostlist_parser::parse("r[1-2]n[1-4]-ib2").unwrap();
+///
+/// assert_eq!(fold_nd(&hosts), "r[1-2]n[1-4]-ib2");
+/// assert_eq!(fold_nd(&hosts[..7]), "r1n[1-4]-ib2,r2n[1-3]-ib2");
+/// ```
+pub fn fold_nd<I: IntoIterator<Item = impl AsRef<str>>>(hosts: I) -> String {
+    let mut groups: BTreeMap<Template, BTreeSet<Vec<u64>>> = BTreeMap::new();
+    let mut literals = BTreeSet::new();
+
+    for host in hosts {
+        let host = host.as_ref();
+
+        match Template::split(host) {
+            Some((template, values)) => {
+                groups.entry(template).or_default().insert(values);
+            }
+            None => {
+                literals.insert(host.to_string());
+            }
+        }
+    }
+
+    merge_padding(groups)
+        .into_iter()
+        .flat_map(|(template, points)| {
+            fold_points(points)
+                .into_iter()
+                .map(move |sets| template.to_ast(&sets))
+        })
+        .chain(
+            literals
+                .into_iter()
+                .map(|x| ast::Hostlist::new(vec![Expr::Literal(x)])),
+        )
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use proptest::prelude::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_fold_nd() {
+        let hosts = parse("r[1-2]n[1-4]-ib2").unwrap();
+
+        assert_eq!(hosts.len(), 8);
+        assert_eq!(fold_nd(&hosts), "r[1-2]n[1-4]-ib2");
+        assert_eq!(
+            fold_nd(parse("r[1-3]n[01-02]-ib[0-1],r4n01-ib0,mds").unwrap()),
+            "r[1-3]n[01-02]-ib[0-1],r4n01-ib0,mds"
+        );
+        assert_eq!(fold_nd(["node1", "node2", "node01"]), "node[1-2],node01");
+        assert_eq!(fold_nd(parse("node[08-12]").unwrap()), "node[08-12]");
+        assert_eq!(fold_nd(["n09", "n10"]), "n[09-10]");
+        assert_eq!(
+            fold_nd(parse("r[098-102]n[1-2]").unwrap()),
+            "r[098-102]n[1-2]"
+        );
+        assert_eq!(fold_nd(Vec::<String>::new()), "");
+    }
+
+    #[test]
+    fn test_fold_nd_only_complete_products() {
+        // The bounding box `r[1-2]n[1-2]` would add r1n2 and r2n1.
+        assert_eq!(fold_nd(["r1n1", "r2n2"]), "r1n1,r2n2");
+
+        // Rows are folded first, then merged where they hold the same values.
+        assert_eq!(
+            fold_nd(["r1n1", "r1n2", "r2n1", "r2n2", "r3n1"]),
+            "r[1-2]n[1-2],r3n1"
+        );
+        assert_eq!(
+            fold_nd(parse("r[1-3]n[1-4],r4n[1-3]").unwrap()),
+            "r[1-3]n[1-4],r4n[1-3]"
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn prop_fold_nd_round_trip(
+            hosts in prop::collection::vec("r[0-3]{1,2}n[0-9]{1,2}(-ib[0-2])?|gw", 1..40)
+        ) {
+            let expr = fold_nd(&hosts);
+
+            let expected: HashSet<_> = hosts.into_iter().collect();
+            let actual: HashSet<_> = parse(&expr).unwrap().into_iter().collect();
+
+            prop_assert_eq!(actual, expected, "{}", expr);
+        }
+    }
+}
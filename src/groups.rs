@@ -0,0 +1,133 @@
+// Copyright (c) 2022 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+use crate::{
+    comma, dash, hostlist, optional_spaces,
+    structures::{Part, Syntax},
+    Error, Expansion,
+};
+use combine::{
+    attempt, eof, error::ParseError, many1, parser::char::alpha_num, sep_by1, stream::Stream,
+    token, EasyParser, Parser,
+};
+use std::collections::HashSet;
+
+/// A comma separated entry of an expression that may name groups.
+enum Entry {
+    Hosts(Vec<Part>),
+    /// `@name`, the hosts of a group.
+    Group(String),
+}
+
+/// A group reference, as in `@gpu`. Names are letters, digits, `-`, `.` and `_`.
+fn group<I>() -> impl Parser<I, Output = String>
+where
+    I: Stream<Token = char>,
+    I::Error: ParseError<I::Token, I::Range, I::Position>,
+{
+    attempt(optional_spaces().with(token('@')))
+        .with(many1(alpha_num().or(dash()).or(token('.')).or(token('_'))))
+}
+
+fn entries<I>() -> impl Parser<I, Output = Vec<Entry>>
+where
+    I: Stream<Token = char>,
+    I::Error: ParseError<I::Token, I::Range, I::Position>,
+{
+    sep_by1(
+        group()
+            .map(Entry::Group)
+            .or(hostlist(Syntax::DEFAULT).map(Entry::Hosts)),
+        optional_spaces().with(comma()),
+    )
+    .skip(eof())
+}
+
+/// Expands `input` like [`crate::parse`], where an entry may also be `@name`, standing for the
+/// hosts `resolver` gives for `name`, as pdsh does with its groups.
+///
+/// The hosts of a group are taken as they are, in the order given, and repeats are dropped across
+/// the whole expression. `resolver` is called for each reference, and a group it returns `None`
+/// for is an [`Error::UnknownGroup`]. A group is a whole entry, so `@gpu[1-2]` is a parse error.
+///
+/// ```
+/// use hostlist_parser::parse_with_groups;
+/// use std::collections::HashMap;
+///
+/// let groups = HashMap::from([("gpu", vec!["gpu1".to_string(), "gpu2".to_string()])]);
+/// let resolve = |name: &str| groups.get(name).cloned();
+///
+/// assert_eq!(
+///     parse_with_groups("@gpu,node[1-2]", resolve).unwrap(),
+///     vec!["gpu1", "gpu2", "node1", "node2"]
+/// );
+/// assert!(parse_with_groups("@cpu", resolve).is_err());
+/// ```
+pub fn parse_with_groups(
+    input: &str,
+    mut resolver: impl FnMut(&str) -> Option<Vec<String>>,
+) -> Result<Vec<String>, Error> {
+    let (entries, _) = entries()
+        .easy_parse(input)
+        .map_err(|err| err.map_position(|p| p.translate_position(input)))?;
+    let mut seen = HashSet::new();
+    let mut xs = vec![];
+
+    for entry in entries {
+        let hosts = match entry {
+            Entry::Hosts(parts) => Expansion::new(vec![parts]).collect(),
+            Entry::Group(group) => match resolver(&group) {
+                Some(hosts) => hosts,
+                None => return Err(Error::UnknownGroup { group }),
+            },
+        };
+
+        xs.extend(hosts.into_iter().filter(|x| seen.insert(x.clone())));
+    }
+
+    Ok(xs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use insta::assert_debug_snapshot;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_parse_with_groups() {
+        let groups = HashMap::from([
+            ("gpu", vec!["gpu1".to_string(), "gpu2".to_string()]),
+            ("io-nodes_2", vec!["oss1".to_string(), "node2".to_string()]),
+            ("empty", vec![]),
+        ]);
+        let mut calls = vec![];
+        let mut resolve = |name: &str| {
+            calls.push(name.to_string());
+
+            groups.get(name).cloned()
+        };
+
+        assert_eq!(
+            parse_with_groups("node[1-2], @io-nodes_2 ,@gpu,@empty,gpu1", &mut resolve).unwrap(),
+            vec!["node1", "node2", "oss1", "gpu1", "gpu2"]
+        );
+        assert_eq!(calls, vec!["io-nodes_2", "gpu", "empty"]);
+        assert_eq!(
+            parse_with_groups("node[1-2]", |_| None).unwrap(),
+            parse("node[1-2]").unwrap()
+        );
+        assert_eq!(
+            parse_with_groups("n1,@rack1", |_| None),
+            Err(Error::UnknownGroup {
+                group: "rack1".into()
+            })
+        );
+
+        assert_debug_snapshot!(parse_with_groups("@gpu[1-2]", |x| groups.get(x).cloned()));
+        assert_debug_snapshot!(parse_with_groups("@gpu!", |_| Some(vec![])));
+        assert!(parse("@gpu").is_err());
+    }
+}
@@ -0,0 +1,2300 @@
+// Copyright (c) 2022 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+use crate::{
+    ast::{self, Expr},
+    parse_hostlists,
+    range_set::{self, RangeSet},
+    structures::{cardinality, digit_bands, host_at, num_digits, Part, RangeOutput, MAX_WIDTH},
+    Error,
+};
+use itertools::Itertools as _;
+use std::{
+    collections::{btree_map, btree_set, BTreeMap, BTreeSet, HashMap},
+    fmt,
+    ops::RangeInclusive,
+    str::FromStr,
+    sync::Arc,
+};
+
+/// The literal skeleton of a host around its last run of digits.
+///
+/// A `width` of `0` means the number is written without leading zeros; otherwise it is zero-padded
+/// to exactly `width` digits and has at least one leading zero.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Shape {
+    prefix: String,
+    suffix: String,
+    width: usize,
+}
+
+impl Shape {
+    /// Splits `host` around its last run of ASCII digits. Returns `None` if there is no such run,
+    /// or if it does not fit in a `u64`.
+    fn split(host: &str) -> Option<(Self, u64)> {
+        let end = host.rfind(|c: char| c.is_ascii_digit())? + 1;
+        let start = host[..end]
+            .rfind(|c: char| !c.is_ascii_digit())
+            .map_or(0, |i| {
+                i + host[i..].chars().next().map_or(1, char::len_utf8)
+            });
+        let digits = &host[start..end];
+        let value = digits.parse::<u64>().ok()?;
+
+        let width = if digits.len() > num_digits(value) {
+            digits.len()
+        } else {
+            0
+        };
+
+        let shape = Shape {
+            prefix: host[..start].to_string(),
+            suffix: host[end..].to_string(),
+            width,
+        };
+
+        Some((shape, value))
+    }
+
+    /// A shape with no text around the number.
+    fn bare(width: usize) -> Self {
+        Self {
+            prefix: String::new(),
+            suffix: String::new(),
+            width,
+        }
+    }
+
+    fn format(&self, value: u64) -> String {
+        let width = self.width;
+
+        format!("{}{value:0>width$}{}", self.prefix, self.suffix)
+    }
+}
+
+/// The numbers of the hosts sharing one literal skeleton, as merged ranges. See
+/// [`Hostlist::ranges`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PrefixRanges {
+    /// The text before the number.
+    pub prefix: String,
+    /// The text after the number.
+    pub suffix: String,
+    /// The zero-padded width of the number, or `0` if it is not padded.
+    pub width: usize,
+    /// Sorted, non-overlapping and non-adjacent ranges.
+    pub ranges: Vec<RangeInclusive<u64>>,
+}
+
+/// A set of hosts.
+///
+/// Hosts are stored as numeric ranges grouped by their literal skeleton rather than as expanded
+/// names, so large contiguous expressions stay small. A host's number is its last run of digits;
+/// hosts without one are kept as literals.
+///
+/// The ranges are shared between clones, so cloning is cheap whatever the number of hosts. They
+/// are only copied when a clone that still shares them is modified.
+///
+/// Two lists are equal, and hash the same, when they hold the same hosts, however they were
+/// written. Padding is part of a host, so `node[01-03]` and `node[1-3]` differ.
+///
+/// ```
+/// use hostlist_parser::Hostlist;
+///
+/// # fn main() -> Result<(), hostlist_parser::Error> {
+/// let h: Hostlist = "oss[1-4].local".parse()?;
+///
+/// for host in &h {
+///     println!("{host}");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+// Ranges are kept merged and sorted, and empty groups are dropped, so the derived comparisons are
+// on the sets of hosts.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Hostlist {
+    groups: Arc<BTreeMap<Shape, RangeSet>>,
+    literals: Arc<BTreeSet<String>>,
+}
+
+impl Hostlist {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The most hosts [`Hostlist::parse`] adds one at a time rather than as ranges.
+    pub const EXPANSION_LIMIT: usize = 1 << 20;
+
+    /// Parses a hostlist expression into a set of hosts.
+    ///
+    /// Hosts are added as ranges when their last run of digits comes from the last bracket group,
+    /// as in `node[1-100].local`. Otherwise, as in `r[1-4]n1` or `n[1-4]-ib0`, each host is
+    /// expanded and added on its own, and [`Error::TooManyHosts`] is returned, before expanding
+    /// anything, if more than [`Hostlist::EXPANSION_LIMIT`] would be.
+    pub fn parse(input: &str) -> Result<Self, Error> {
+        let hosts = parse_hostlists(input)?;
+        let mut hostlist = Self::new();
+
+        check_expansion(&hosts)?;
+
+        for parts in hosts {
+            hostlist.add_parts(&parts);
+        }
+
+        Ok(hostlist)
+    }
+
+    /// The hosts of a single parsed hostlist.
+    pub(crate) fn from_parts(parts: &[Part]) -> Self {
+        let mut hostlist = Self::new();
+
+        hostlist.add_parts(parts);
+
+        hostlist
+    }
+
+    /// The hosts of a single parsed hostlist, or `None` if more than `limit` of them, or of their
+    /// prefixes, would be added one at a time.
+    pub(crate) fn fold(parts: &[Part], limit: u128) -> Option<Self> {
+        (unfolded_len(parts) <= limit).then(|| Self::from_parts(parts))
+    }
+
+    /// The number of distinct hosts, counted from the stored ranges without expanding them.
+    ///
+    /// This is a `u128` as a single bracket group can cover every `u64`.
+    pub fn len(&self) -> u128 {
+        self.groups
+            .values()
+            .map(RangeSet::len)
+            .fold(self.literals.len() as u128, u128::saturating_add)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty() && self.literals.is_empty()
+    }
+
+    /// Adds a single host, extending the range it continues. Returns whether the host was new.
+    ///
+    /// `host` is taken as a name, not an expression: anything holding brackets or commas is
+    /// refused with [`Error::NotAHost`].
+    pub fn insert(&mut self, host: &str) -> Result<bool, Error> {
+        if host.is_empty() || host.contains(['[', ']', ',']) {
+            return Err(Error::NotAHost {
+                host: host.to_string(),
+            });
+        }
+
+        let new = !self.contains(host);
+
+        if new {
+            self.add(host);
+        }
+
+        Ok(new)
+    }
+
+    /// Removes a single host, splitting the range it was in. Returns whether the host was present.
+    pub fn remove(&mut self, host: &str) -> bool {
+        if !self.contains(host) {
+            return false;
+        }
+
+        let Some((shape, value)) = Shape::split(host) else {
+            return Arc::make_mut(&mut self.literals).remove(host);
+        };
+
+        let groups = Arc::make_mut(&mut self.groups);
+
+        if let Some(set) = groups.get_mut(&shape) {
+            set.remove(value, value);
+
+            if set.is_empty() {
+                groups.remove(&shape);
+            }
+        }
+
+        true
+    }
+
+    /// Whether `host` is in the list, padding included. Only the stored ranges are searched.
+    pub fn contains(&self, host: &str) -> bool {
+        match Shape::split(host) {
+            Some((shape, value)) => self.groups.get(&shape).is_some_and(|x| x.contains(value)),
+            None => self.literals.contains(host),
+        }
+    }
+
+    /// The index of `host` in iteration order, or `None` if it is not in the list, padding
+    /// included. It is counted on the ranges, without going through the hosts before it.
+    ///
+    /// Also `None` if the index does not fit in a `usize`.
+    ///
+    /// ```
+    /// use hostlist_parser::Hostlist;
+    ///
+    /// let h = Hostlist::parse("node[1-10,20-29],mds").unwrap();
+    ///
+    /// assert_eq!(h.position("node21"), Some(11));
+    /// assert_eq!(h.position("mds"), Some(20));
+    /// assert_eq!(h.position("node01"), None);
+    /// ```
+    pub fn position(&self, host: &str) -> Option<usize> {
+        let numbered = |groups: btree_map::Range<'_, Shape, RangeSet>| -> u128 {
+            groups
+                .map(|(_, set)| set.len())
+                .fold(0, u128::saturating_add)
+        };
+
+        let index = match Shape::split(host) {
+            Some((shape, value)) => {
+                let rank = self.groups.get(&shape)?.rank(value)?;
+
+                numbered(self.groups.range(..shape)).saturating_add(rank)
+            }
+            None => {
+                if !self.literals.contains(host) {
+                    return None;
+                }
+
+                let rank = self
+                    .literals
+                    .iter()
+                    .take_while(|x| x.as_str() < host)
+                    .count();
+
+                numbered(self.groups.range(..)).saturating_add(rank as u128)
+            }
+        };
+
+        usize::try_from(index).ok()
+    }
+
+    /// The smallest number used between `prefix` and `suffix`, with the width it is padded to (`0`
+    /// if it is not). Only the stored ranges are looked at.
+    ///
+    /// Returns `None` if no host has that literal skeleton. A number written both with and without
+    /// padding is reported unpadded.
+    ///
+    /// ```
+    /// use hostlist_parser::Hostlist;
+    ///
+    /// let h = Hostlist::parse("oss[03-12].local,oss1.local").unwrap();
+    ///
+    /// assert_eq!(h.min_index("oss", ".local"), Some((1, 0)));
+    /// assert_eq!(h.max_index("oss", ".local"), Some((12, 0)));
+    /// assert_eq!(h.min_index("mds", ""), None);
+    /// ```
+    pub fn min_index(&self, prefix: &str, suffix: &str) -> Option<(u64, usize)> {
+        self.indices(prefix, suffix)
+            .filter_map(|(set, width)| Some((set.iter().next()?.0, width)))
+            .min()
+    }
+
+    /// The largest number used between `prefix` and `suffix`, with the width it is padded to. See
+    /// [`Hostlist::min_index`].
+    pub fn max_index(&self, prefix: &str, suffix: &str) -> Option<(u64, usize)> {
+        self.indices(prefix, suffix)
+            .filter_map(|(set, width)| Some((set.iter().next_back()?.1, width)))
+            .max_by_key(|&(value, width)| (value, std::cmp::Reverse(width)))
+    }
+
+    /// The numbers used between `prefix` and `suffix`, for each padding.
+    fn indices<'a>(
+        &'a self,
+        prefix: &'a str,
+        suffix: &'a str,
+    ) -> impl Iterator<Item = (&'a RangeSet, usize)> {
+        self.groups
+            .iter()
+            .filter(move |(shape, _)| shape.prefix == prefix && shape.suffix == suffix)
+            .map(|(shape, set)| (set, shape.width))
+    }
+
+    /// The hosts in either list. Ranges sharing the same literal skeleton and padding are merged.
+    pub fn union(&self, other: &Hostlist) -> Hostlist {
+        let mut hostlist = self.clone();
+
+        for (shape, set) in other.groups.iter() {
+            for (lo, hi) in set.iter() {
+                hostlist.add_range(shape.clone(), lo, hi);
+            }
+        }
+
+        if !other.literals.is_empty() {
+            Arc::make_mut(&mut hostlist.literals).extend(other.literals.iter().cloned());
+        }
+
+        hostlist
+    }
+
+    /// The hosts in both lists, computed on the ranges of each literal skeleton. Hosts that only
+    /// differ in padding do not match.
+    pub fn intersection(&self, other: &Hostlist) -> Hostlist {
+        let groups = self
+            .groups
+            .iter()
+            .filter_map(|(shape, set)| {
+                let set = set.intersection(other.groups.get(shape)?);
+
+                (!set.is_empty()).then(|| (shape.clone(), set))
+            })
+            .collect();
+
+        Hostlist {
+            groups: Arc::new(groups),
+            literals: Arc::new(
+                self.literals
+                    .intersection(&other.literals)
+                    .cloned()
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Whether no host is in both lists. Like [`Hostlist::intersection`], ranges are compared
+    /// without expanding them and hosts that only differ in padding do not match, but this stops
+    /// at the first shared host.
+    pub fn is_disjoint(&self, other: &Hostlist) -> bool {
+        self.groups
+            .iter()
+            .all(|(shape, set)| other.groups.get(shape).is_none_or(|x| set.is_disjoint(x)))
+            && self.literals.is_disjoint(&other.literals)
+    }
+
+    /// The hosts in this list but not in `other`. Ranges are split around the removed hosts, and
+    /// removing hosts that are not present changes nothing.
+    pub fn difference(&self, other: &Hostlist) -> Hostlist {
+        let groups = self
+            .groups
+            .iter()
+            .filter_map(|(shape, set)| {
+                let set = match other.groups.get(shape) {
+                    Some(x) => set.difference(x),
+                    None => set.clone(),
+                };
+
+                (!set.is_empty()).then(|| (shape.clone(), set))
+            })
+            .collect();
+
+        Hostlist {
+            groups: Arc::new(groups),
+            literals: Arc::new(self.literals.difference(&other.literals).cloned().collect()),
+        }
+    }
+
+    /// The hosts in exactly one of the two lists, computed on ranges as
+    /// `(self ∪ other) − (self ∩ other)`.
+    pub fn symmetric_difference(&self, other: &Hostlist) -> Hostlist {
+        self.union(other).difference(&self.intersection(other))
+    }
+
+    /// Keeps only the hosts `f` accepts, called once per host in iteration order. Runs of accepted
+    /// hosts are stored as ranges again, so the result folds like any other list.
+    ///
+    /// ```
+    /// use hostlist_parser::Hostlist;
+    /// use std::collections::HashSet;
+    ///
+    /// let drained: HashSet<_> = ["node3", "node4", "mds1"].into();
+    /// let h = Hostlist::parse("node[1-8],mds[1-2]").unwrap();
+    ///
+    /// assert_eq!(
+    ///     h.filter(|x| !drained.contains(x)).to_string(),
+    ///     "mds2,node[1-2,5-8]"
+    /// );
+    /// ```
+    pub fn filter(mut self, f: impl FnMut(&str) -> bool) -> Hostlist {
+        self.retain(f);
+        self
+    }
+
+    /// Removes the hosts `f` rejects, in place, like [`Hostlist::filter`]. Ranges are split
+    /// around the removed hosts, down to a range per host when every other one is removed.
+    pub fn retain(&mut self, mut f: impl FnMut(&str) -> bool) {
+        let groups = Arc::make_mut(&mut self.groups);
+
+        groups.retain(|shape, set| {
+            set.retain(|value| f(&shape.format(value)));
+
+            !set.is_empty()
+        });
+
+        if !self.literals.is_empty() {
+            Arc::make_mut(&mut self.literals).retain(|x| f(x));
+        }
+    }
+
+    /// Summarizes the numbers of the hosts for each literal skeleton, without expanding them, in
+    /// iteration order.
+    ///
+    /// As everywhere in a `Hostlist`, a host's number is its last run of digits, so other bracket
+    /// groups end up in the prefix: `r[1-2]n[1-4]` gives one entry for `r1n` and one for `r2n`.
+    /// Hosts without a number are not included.
+    pub fn ranges(&self) -> Vec<PrefixRanges> {
+        self.groups
+            .iter()
+            .map(|(shape, set)| PrefixRanges {
+                prefix: shape.prefix.clone(),
+                suffix: shape.suffix.clone(),
+                width: shape.width,
+                ranges: set.iter().map(|(lo, hi)| lo..=hi).collect(),
+            })
+            .collect()
+    }
+
+    /// Iterates over the hosts starting `n` positions into the iteration order, wrapping around to
+    /// the start, so `node[1-4]` rotated by 1 gives `node2`, `node3`, `node4`, `node1`.
+    ///
+    /// `n` is taken modulo [`Hostlist::len`]. The split is made on the ranges, so nothing is
+    /// expanded ahead of the caller, and every host is still visited exactly once.
+    pub fn rotate(&self, n: usize) -> impl Iterator<Item = String> {
+        let mut rest = self.clone();
+        let len = self.len();
+        let head = if len == 0 {
+            Hostlist::new()
+        } else {
+            rest.take_first(n as u128 % len)
+        };
+
+        rest.into_iter().chain(head)
+    }
+
+    /// Iterates over up to `len` hosts, starting `offset` positions into the iteration order, to
+    /// show one page of a large list.
+    ///
+    /// The hosts before `offset` are skipped on the ranges rather than generated, and an `offset`
+    /// past the end gives no hosts.
+    ///
+    /// ```
+    /// use hostlist_parser::Hostlist;
+    ///
+    /// let h = Hostlist::parse("node[1-100000]").unwrap();
+    ///
+    /// assert_eq!(h.paged(50, 2).collect::<Vec<_>>(), vec!["node51", "node52"]);
+    /// assert_eq!(h.paged(100_000, 50).count(), 0);
+    /// ```
+    pub fn paged(&self, offset: usize, len: usize) -> impl Iterator<Item = String> {
+        let mut rest = self.clone();
+
+        rest.take_first(offset as u128);
+
+        rest.into_iter().take(len)
+    }
+
+    /// Removes and returns the first `count` hosts in iteration order, splitting ranges as needed.
+    pub(crate) fn take_first(&mut self, mut count: u128) -> Hostlist {
+        let mut taken = Hostlist::new();
+
+        if count == 0 {
+            return taken;
+        }
+
+        let (groups, literals) = (
+            Arc::make_mut(&mut self.groups),
+            Arc::make_mut(&mut self.literals),
+        );
+        let (taken_groups, taken_literals) = (
+            Arc::make_mut(&mut taken.groups),
+            Arc::make_mut(&mut taken.literals),
+        );
+
+        while count > 0 {
+            let Some(mut entry) = groups.first_entry() else {
+                break;
+            };
+
+            let set = entry.get_mut().take_first(count);
+
+            count -= set.len();
+
+            let shape = if entry.get().is_empty() {
+                entry.remove_entry().0
+            } else {
+                entry.key().clone()
+            };
+
+            taken_groups.insert(shape, set);
+        }
+
+        while count > 0 {
+            let Some(host) = literals.pop_first() else {
+                break;
+            };
+
+            taken_literals.insert(host);
+            count -= 1;
+        }
+
+        taken
+    }
+
+    /// The first host in iteration order.
+    pub(crate) fn first(&self) -> Option<String> {
+        self.iter().next().map(String::from)
+    }
+
+    /// The last host in iteration order, found without walking the ranges.
+    pub(crate) fn last(&self) -> Option<String> {
+        if let Some(host) = self.literals.last() {
+            return Some(host.clone());
+        }
+
+        let (shape, set) = self.groups.last_key_value()?;
+
+        Some(shape.format(set.iter().next_back()?.1))
+    }
+
+    /// Removes and returns the first host in iteration order, shrinking its range, like
+    /// [`BTreeSet::pop_first`].
+    ///
+    /// ```
+    /// use hostlist_parser::Hostlist;
+    ///
+    /// let mut free = Hostlist::parse("node[1-3],login").unwrap();
+    ///
+    /// assert_eq!(free.pop_first().as_deref(), Some("node1"));
+    /// assert_eq!(free.pop_last().as_deref(), Some("login"));
+    /// assert_eq!(free.to_string(), "node[2-3]");
+    /// ```
+    pub fn pop_first(&mut self) -> Option<String> {
+        self.take_first(1).into_iter().next()
+    }
+
+    /// Removes and returns the last host in iteration order, shrinking its range, like
+    /// [`BTreeSet::pop_last`].
+    pub fn pop_last(&mut self) -> Option<String> {
+        if !self.literals.is_empty() {
+            return Arc::make_mut(&mut self.literals).pop_last();
+        }
+
+        let mut entry = Arc::make_mut(&mut self.groups).last_entry()?;
+        let (_, value) = entry.get().iter().next_back()?;
+        let host = entry.key().format(value);
+
+        entry.get_mut().remove(value, value);
+
+        if entry.get().is_empty() {
+            entry.remove();
+        }
+
+        Some(host)
+    }
+
+    /// The number of contiguous runs of hosts, counting each host without a number as one.
+    pub(crate) fn run_count(&self) -> usize {
+        self.groups
+            .values()
+            .map(|set| set.iter().len())
+            .sum::<usize>()
+            + self.literals.len()
+    }
+
+    /// Iterates over the hosts, grouped by literal skeleton with numbers ascending, followed by the
+    /// hosts without a number.
+    pub fn iter(&self) -> Hosts<'_> {
+        Hosts {
+            groups: self.groups.iter(),
+            front: None,
+            back: None,
+            literals: self.literals.iter(),
+        }
+    }
+
+    fn add(&mut self, host: &str) {
+        match Shape::split(host) {
+            Some((shape, value)) => self.add_range(shape, value, value),
+            None => {
+                Arc::make_mut(&mut self.literals).insert(host.to_string());
+            }
+        }
+    }
+
+    fn add_range(&mut self, shape: Shape, lo: u64, hi: u64) {
+        Arc::make_mut(&mut self.groups)
+            .entry(shape)
+            .or_default()
+            .insert(lo, hi);
+    }
+
+    /// Adds the hosts of a single parsed hostlist. When the last bracket group provides the last
+    /// run of digits of every host, its ranges are added whole; otherwise each host is expanded.
+    fn add_parts(&mut self, parts: &[Part]) {
+        if !parts.iter().any(|p| matches!(p, Part::Range(_))) {
+            return self.add(&host_at(parts, 0));
+        }
+
+        let Some((head, ranges, suffix)) = last_group(parts) else {
+            for i in 0..cardinality(parts) {
+                self.add(&host_at(parts, i));
+            }
+
+            return;
+        };
+
+        for i in 0..cardinality(head) {
+            let prefix = host_at(head, i);
+
+            for x in ranges {
+                self.add_range_output(&prefix, &suffix, x);
+            }
+        }
+    }
+
+    fn add_range_output(&mut self, prefix: &str, suffix: &str, x: &RangeOutput) {
+        let shape = |width| Shape {
+            prefix: prefix.to_string(),
+            suffix: suffix.to_string(),
+            width,
+        };
+
+        let (prefix_zeros, same_prefix_len, lo, hi) = match x {
+            RangeOutput::Range(prefix_zeros, same_prefix_len, lo, hi)
+            | RangeOutput::RangeReversed(prefix_zeros, same_prefix_len, lo, hi) => {
+                (*prefix_zeros, *same_prefix_len, *lo, *hi)
+            }
+            RangeOutput::Disjoint(_) | RangeOutput::Stepped(..) | RangeOutput::Words(_) => {
+                for i in 0..x.cardinality() {
+                    self.add(&format!("{prefix}{}{suffix}", x.format_at(i as u64)));
+                }
+
+                return;
+            }
+        };
+
+        match (prefix_zeros, same_prefix_len) {
+            (0, true) => self.add_range(shape(0), lo, hi),
+            // Every value keeps `prefix_zeros` leading zeros, so the width grows with the value.
+            (prefix_zeros, true) => {
+                for (digits, band) in digit_bands(lo, hi) {
+                    self.add_range(shape(prefix_zeros + digits), *band.start(), *band.end());
+                }
+            }
+            // Values are padded to a fixed width; those that fill it have no leading zeros.
+            (prefix_zeros, false) => {
+                let width = prefix_zeros + 1;
+
+                for (digits, band) in digit_bands(lo, hi) {
+                    let width = if digits < width { width } else { 0 };
+
+                    self.add_range(shape(width), *band.start(), *band.end());
+                }
+            }
+        }
+    }
+}
+
+/// Splits a single parsed hostlist into the parts before its last bracket group, the ranges of
+/// that group and the text after it. Returns `None` if there is no bracket group, or if it does not
+/// provide the last run of digits of every host.
+fn last_group(parts: &[Part]) -> Option<(&[Part], &[RangeOutput], String)> {
+    let last = parts.iter().rposition(|p| matches!(p, Part::Range(_)))?;
+
+    let (head, tail) = parts.split_at(last);
+    let (ranges, tail) = match tail {
+        [Part::Range(xs), tail @ ..] => (xs, tail),
+        _ => unreachable!(),
+    };
+
+    let suffix: String = tail
+        .iter()
+        .map(|p| match p {
+            Part::String(x) => x.as_str(),
+            Part::Range(_) => unreachable!(),
+        })
+        .collect();
+
+    let digit_before = match head.last() {
+        Some(Part::String(x)) => x.ends_with(|c: char| c.is_ascii_digit()),
+        Some(Part::Range(_)) => true,
+        None => false,
+    };
+
+    if digit_before || suffix.contains(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+
+    Some((head, ranges, suffix))
+}
+
+/// The number of hosts, or prefixes, [`Hostlist::add_parts`] adds one at a time for a single
+/// parsed hostlist. A host without a bracket group is not counted, as it is written out already.
+fn unfolded_len(parts: &[Part]) -> u128 {
+    if !parts.iter().any(|p| matches!(p, Part::Range(_))) {
+        return 0;
+    }
+
+    let Some((head, ranges, _)) = last_group(parts) else {
+        return cardinality(parts);
+    };
+
+    ranges
+        .iter()
+        .map(|x| match x {
+            RangeOutput::Range(..) | RangeOutput::RangeReversed(..) => 1,
+            _ => x.cardinality(),
+        })
+        .fold(0u128, u128::saturating_add)
+        .saturating_mul(cardinality(head))
+}
+
+/// Fails with [`Error::TooManyHosts`] if [`Hostlist::add_parts`] would add more than
+/// [`Hostlist::EXPANSION_LIMIT`] hosts one at a time for `hosts`.
+pub(crate) fn check_expansion(hosts: &[Vec<Part>]) -> Result<(), Error> {
+    let count = hosts
+        .iter()
+        .fold(0u128, |acc, parts| acc.saturating_add(unfolded_len(parts)));
+
+    if count > Hostlist::EXPANSION_LIMIT as u128 {
+        return Err(Error::TooManyHosts {
+            count,
+            limit: Hostlist::EXPANSION_LIMIT,
+        });
+    }
+
+    Ok(())
+}
+
+impl Hostlist {
+    /// Rewrites every number to the widest zero padding used with the same prefix and suffix, so
+    /// `node1` and `node01` both become `node01`. Numbers already as wide are left alone.
+    pub(crate) fn merge_padding(&mut self) {
+        let mut widths: BTreeMap<(&str, &str), usize> = BTreeMap::new();
+
+        for shape in self.groups.keys() {
+            let width = widths.entry((&shape.prefix, &shape.suffix)).or_default();
+
+            *width = (*width).max(shape.width);
+        }
+
+        let mut groups: BTreeMap<Shape, RangeSet> = BTreeMap::new();
+
+        for (shape, set) in self.groups.iter() {
+            let widest = widths[&(shape.prefix.as_str(), shape.suffix.as_str())];
+
+            for (lo, hi) in set.iter() {
+                for (digits, band) in digit_bands(lo, hi) {
+                    let width = if digits < widest { widest } else { 0 };
+
+                    groups
+                        .entry(Shape {
+                            width,
+                            ..shape.clone()
+                        })
+                        .or_default()
+                        .insert(*band.start(), *band.end());
+                }
+            }
+        }
+
+        self.groups = Arc::new(groups);
+    }
+
+    /// Folds the set into one entry per prefix and suffix pair, in sorted order, followed by the
+    /// hosts without a number.
+    fn to_ast(&self) -> Vec<ast::Hostlist> {
+        let mut xs = vec![];
+        let mut groups = self.groups.iter().peekable();
+
+        while let Some((shape, set)) = groups.next() {
+            let mut members: Vec<_> = set.iter().map(|(lo, hi)| (lo, hi, shape.width)).collect();
+
+            while let Some((next, set)) =
+                groups.next_if(|(x, _)| x.prefix == shape.prefix && x.suffix == shape.suffix)
+            {
+                members.extend(set.iter().map(|(lo, hi)| (lo, hi, next.width)));
+            }
+
+            members.sort_unstable();
+
+            xs.push(fold_group(&shape.prefix, &shape.suffix, members));
+        }
+
+        xs.extend(
+            self.literals
+                .iter()
+                .map(|x| ast::Hostlist::new(vec![Expr::Literal(x.clone())])),
+        );
+
+        xs
+    }
+
+    /// The entries [`Hostlist::to_ast`] writes, in the same order: a prefix and suffix pair for
+    /// each folded group, then the hosts without a number.
+    fn entry_keys(&self) -> Vec<(String, Option<String>)> {
+        let groups = self
+            .groups
+            .keys()
+            .map(|x| (x.prefix.clone(), Some(x.suffix.clone())))
+            .dedup();
+
+        groups
+            .chain(self.literals.iter().map(|x| (x.clone(), None)))
+            .collect()
+    }
+
+    /// Parses `input` and folds it like `Display`, but with entries in the order they first
+    /// appear in `input` rather than sorted.
+    pub(crate) fn compact(input: &str) -> Result<String, Error> {
+        let mut hostlist = Self::new();
+        let mut rank = HashMap::new();
+        let hosts = parse_hostlists(input)?;
+
+        check_expansion(&hosts)?;
+
+        for parts in hosts {
+            let x = Self::from_parts(&parts);
+
+            for key in x.entry_keys() {
+                let next = rank.len();
+
+                rank.entry(key).or_insert(next);
+            }
+
+            hostlist = hostlist.union(&x);
+        }
+
+        let mut xs: Vec<_> = hostlist
+            .entry_keys()
+            .into_iter()
+            .map(|key| rank[&key])
+            .zip(hostlist.to_ast())
+            .collect();
+
+        xs.sort_by_key(|(rank, _)| *rank);
+
+        Ok(xs.iter().map(|(_, x)| x).join(","))
+    }
+}
+
+/// The version written first by [`Hostlist::to_bytes`].
+const ENCODING_VERSION: u8 = 1;
+
+impl Hostlist {
+    /// Encodes the set compactly, as its literal skeletons and numeric ranges rather than the
+    /// expanded names, so a contiguous range takes a few bytes whatever its size. Read it back with
+    /// [`Hostlist::from_bytes`].
+    ///
+    /// The first byte is the version of the encoding. Numbers are LEB128 varints, and each range is
+    /// written as the gap from the previous one and its length.
+    ///
+    /// ```
+    /// use hostlist_parser::Hostlist;
+    ///
+    /// let h = Hostlist::parse("node[1-50000].local").unwrap();
+    /// let bytes = h.to_bytes();
+    ///
+    /// assert!(bytes.len() < 32);
+    /// assert_eq!(Hostlist::from_bytes(&bytes).unwrap().to_string(), h.to_string());
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![ENCODING_VERSION];
+
+        put_varint(&mut out, self.groups.len() as u64);
+
+        for (shape, set) in self.groups.iter() {
+            put_str(&mut out, &shape.prefix);
+            put_str(&mut out, &shape.suffix);
+            put_varint(&mut out, shape.width as u64);
+            put_varint(&mut out, set.iter().len() as u64);
+
+            let mut last = None;
+
+            for (lo, hi) in set.iter() {
+                put_varint(&mut out, last.map_or(lo, |last| lo - last - 2));
+                put_varint(&mut out, hi - lo);
+                last = Some(hi);
+            }
+        }
+
+        put_varint(&mut out, self.literals.len() as u64);
+
+        for host in self.literals.iter() {
+            put_str(&mut out, host);
+        }
+
+        out
+    }
+
+    /// Decodes a set written by [`Hostlist::to_bytes`].
+    ///
+    /// An encoding version this crate does not know fails with [`Error::UnsupportedVersion`], and
+    /// bytes that are not a valid encoding fail with [`Error::InvalidEncoding`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut decoder = Decoder { bytes, pos: 0 };
+
+        match decoder.byte()? {
+            ENCODING_VERSION => {}
+            version => return Err(Error::UnsupportedVersion { version }),
+        }
+
+        let mut hostlist = Hostlist::new();
+
+        for _ in 0..decoder.varint()? {
+            let offset = decoder.pos;
+            let prefix = decoder.str()?;
+            let suffix = decoder.str()?;
+            let width = usize::try_from(decoder.varint()?)
+                .ok()
+                .filter(|width| *width <= MAX_WIDTH)
+                .ok_or_else(|| decoder.error())?;
+            let shape = Shape {
+                prefix,
+                suffix,
+                width,
+            };
+            let mut last = None;
+
+            for _ in 0..decoder.varint()? {
+                let lo = match last {
+                    None => decoder.varint()?,
+                    Some(last) => decoder.checked_add(last, 2)?,
+                };
+                let hi = decoder.checked_add(lo, 0)?;
+
+                // Every host must split back into the same skeleton, or lookups would miss it.
+                for value in [lo, hi] {
+                    if Shape::split(&shape.format(value)).as_ref() != Some(&(shape.clone(), value))
+                    {
+                        return Err(Error::InvalidEncoding { offset });
+                    }
+                }
+
+                hostlist.add_range(shape.clone(), lo, hi);
+                last = Some(hi);
+            }
+        }
+
+        for _ in 0..decoder.varint()? {
+            let offset = decoder.pos;
+            let host = decoder.str()?;
+
+            if Shape::split(&host).is_some() {
+                return Err(Error::InvalidEncoding { offset });
+            }
+
+            Arc::make_mut(&mut hostlist.literals).insert(host);
+        }
+
+        if decoder.pos != bytes.len() {
+            return Err(decoder.error());
+        }
+
+        Ok(hostlist)
+    }
+}
+
+fn put_varint(out: &mut Vec<u8>, mut x: u64) {
+    while x >= 0x80 {
+        out.push(x as u8 | 0x80);
+        x >>= 7;
+    }
+
+    out.push(x as u8);
+}
+
+fn put_str(out: &mut Vec<u8>, x: &str) {
+    put_varint(out, x.len() as u64);
+    out.extend_from_slice(x.as_bytes());
+}
+
+/// Reads the encoding of [`Hostlist::to_bytes`].
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Decoder<'_> {
+    fn error(&self) -> Error {
+        Error::InvalidEncoding { offset: self.pos }
+    }
+
+    fn byte(&mut self) -> Result<u8, Error> {
+        let x = *self.bytes.get(self.pos).ok_or_else(|| self.error())?;
+
+        self.pos += 1;
+
+        Ok(x)
+    }
+
+    fn varint(&mut self) -> Result<u64, Error> {
+        let start = self.pos;
+        let mut x = 0u64;
+
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            let bits = u64::from(byte & 0x7f);
+
+            if bits << shift >> shift != bits {
+                break;
+            }
+
+            x |= bits << shift;
+
+            if byte & 0x80 == 0 {
+                return Ok(x);
+            }
+        }
+
+        Err(Error::InvalidEncoding { offset: start })
+    }
+
+    /// Reads a varint and adds it to `base` plus `extra`, failing on overflow.
+    fn checked_add(&mut self, base: u64, extra: u64) -> Result<u64, Error> {
+        let start = self.pos;
+
+        self.varint()?
+            .checked_add(base)
+            .and_then(|x| x.checked_add(extra))
+            .ok_or(Error::InvalidEncoding { offset: start })
+    }
+
+    fn str(&mut self) -> Result<String, Error> {
+        let start = self.pos;
+        let len = usize::try_from(self.varint()?).map_err(|_| self.error())?;
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or(Error::InvalidEncoding { offset: start })?;
+        let x = std::str::from_utf8(&self.bytes[self.pos..end])
+            .map_err(|_| Error::InvalidEncoding { offset: start })?;
+
+        self.pos = end;
+
+        Ok(x.to_string())
+    }
+}
+
+/// Writes the `(lo, hi, width)` members of a single prefix and suffix pair as one bracket group.
+///
+/// A padded range is merged with the natural range that continues it at full width, so
+/// `node08,node09,node10` becomes `node[08-10]`.
+fn fold_group(prefix: &str, suffix: &str, members: Vec<(u64, u64, usize)>) -> ast::Hostlist {
+    let mut ranges: Vec<ast::Range> = vec![];
+
+    for (lo, hi, width) in members {
+        if let Some(last) = ranges.last_mut() {
+            if width == 0
+                && last.width == num_digits(lo)
+                && last.width == num_digits(hi)
+                && last.end.checked_add(1) == Some(lo)
+            {
+                last.end = hi;
+                continue;
+            }
+        }
+
+        ranges.push(ast::Range::new(lo, hi).with_width(width));
+    }
+
+    let exprs = match &ranges[..] {
+        [x] if x.start == x.end => {
+            let width = x.width;
+
+            vec![Expr::Literal(format!(
+                "{prefix}{:0>width$}{suffix}",
+                x.start
+            ))]
+        }
+        _ => vec![
+            Expr::Literal(prefix.to_string()),
+            Expr::Bracket(ranges),
+            Expr::Literal(suffix.to_string()),
+        ],
+    };
+
+    ast::Hostlist::new(
+        exprs
+            .into_iter()
+            .filter(|x| !matches!(x, Expr::Literal(x) if x.is_empty()))
+            .collect(),
+    )
+}
+
+impl FromStr for Hostlist {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// Builds a list from host names, folding them into ranges as they are added. Names without a run
+/// of digits are kept as literals.
+impl<S: AsRef<str>> FromIterator<S> for Hostlist {
+    fn from_iter<I: IntoIterator<Item = S>>(iter: I) -> Self {
+        let mut hostlist = Self::new();
+
+        hostlist.extend(iter);
+
+        hostlist
+    }
+}
+
+impl<S: AsRef<str>> Extend<S> for Hostlist {
+    fn extend<I: IntoIterator<Item = S>>(&mut self, iter: I) {
+        for host in iter {
+            self.add(host.as_ref());
+        }
+    }
+}
+
+/// Writes the set as a folded expression.
+///
+/// Hosts sharing the text around their number are folded into one bracket group, with contiguous
+/// numbers written as ranges and zero padding kept as is. Entries are sorted by that text and
+/// ranges ascend, so equal sets are always written the same way; hosts without a number come last.
+/// Parsing the output gives back the same set.
+///
+/// ```
+/// use hostlist_parser::Hostlist;
+///
+/// let h: Hostlist = "node4,node[1-3],node07".parse().unwrap();
+///
+/// assert_eq!(h.to_string(), "node[1-4,07]");
+/// ```
+impl fmt::Display for Hostlist {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, x) in self.to_ast().iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+
+            write!(f, "{x}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A host borrowed from a [`Hostlist`].
+///
+/// The name is only built when asked for, with `to_string` or `String::from`. It compares equal to
+/// the `str` it spells.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Host<'a>(Repr<'a>);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Repr<'a> {
+    Numbered(&'a Shape, u64),
+    Literal(&'a str),
+}
+
+impl Host<'_> {
+    fn eq_str(&self, other: &str) -> bool {
+        match self.0 {
+            Repr::Numbered(shape, value) => other
+                .strip_prefix(shape.prefix.as_str())
+                .and_then(|x| x.strip_suffix(shape.suffix.as_str()))
+                .is_some_and(|x| Shape::split(x) == Some((Shape::bare(shape.width), value))),
+            Repr::Literal(x) => x == other,
+        }
+    }
+}
+
+impl fmt::Display for Host<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Repr::Numbered(shape, value) => {
+                let width = shape.width;
+
+                write!(f, "{}{value:0>width$}{}", shape.prefix, shape.suffix)
+            }
+            Repr::Literal(x) => write!(f, "{x}"),
+        }
+    }
+}
+
+impl fmt::Debug for Host<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.to_string(), f)
+    }
+}
+
+impl From<Host<'_>> for String {
+    fn from(host: Host<'_>) -> Self {
+        host.to_string()
+    }
+}
+
+impl PartialEq<str> for Host<'_> {
+    fn eq(&self, other: &str) -> bool {
+        self.eq_str(other)
+    }
+}
+
+impl PartialEq<&str> for Host<'_> {
+    fn eq(&self, other: &&str) -> bool {
+        self.eq_str(other)
+    }
+}
+
+impl PartialEq<String> for Host<'_> {
+    fn eq(&self, other: &String) -> bool {
+        self.eq_str(other)
+    }
+}
+
+/// The position of an iterator within a single group.
+struct Group<S, I> {
+    shape: S,
+    ranges: I,
+    front: Option<RangeInclusive<u64>>,
+    back: Option<RangeInclusive<u64>>,
+}
+
+impl<S, I: DoubleEndedIterator<Item = (u64, u64)>> Group<S, I> {
+    fn new(shape: S, ranges: I) -> Self {
+        Self {
+            shape,
+            ranges,
+            front: None,
+            back: None,
+        }
+    }
+
+    fn next_value(&mut self) -> Option<u64> {
+        loop {
+            if let Some(value) = self.front.as_mut().and_then(Iterator::next) {
+                return Some(value);
+            }
+
+            match self.ranges.next() {
+                Some((lo, hi)) => self.front = Some(lo..=hi),
+                None => return self.back.as_mut()?.next(),
+            }
+        }
+    }
+
+    /// Skips up to `n` values from the front, counting them on the ranges, and returns how many
+    /// were skipped.
+    fn skip_values(&mut self, n: u128) -> u128 {
+        let mut skipped = 0;
+
+        loop {
+            let values = match self.front.as_mut() {
+                Some(values) if !values.is_empty() => values,
+                _ => match self.ranges.next() {
+                    Some((lo, hi)) => self.front.insert(lo..=hi),
+                    None => match self.back.as_mut() {
+                        Some(values) if !values.is_empty() => values,
+                        _ => return skipped,
+                    },
+                },
+            };
+            let (lo, hi) = (*values.start(), *values.end());
+            let len = u128::from(hi - lo) + 1;
+
+            if n - skipped < len {
+                *values = lo + (n - skipped) as u64..=hi;
+
+                return n;
+            }
+
+            skipped += len;
+            *values = hi..=hi;
+            values.next();
+        }
+    }
+
+    fn next_back_value(&mut self) -> Option<u64> {
+        loop {
+            if let Some(value) = self.back.as_mut().and_then(DoubleEndedIterator::next_back) {
+                return Some(value);
+            }
+
+            match self.ranges.next_back() {
+                Some((lo, hi)) => self.back = Some(lo..=hi),
+                None => return self.front.as_mut()?.next_back(),
+            }
+        }
+    }
+}
+
+/// An iterator over the hosts of a [`Hostlist`], borrowing them as [`Host`]s. It can be walked
+/// from both ends.
+pub struct Hosts<'a> {
+    groups: btree_map::Iter<'a, Shape, RangeSet>,
+    front: Option<Group<&'a Shape, range_set::Iter<'a>>>,
+    back: Option<Group<&'a Shape, range_set::Iter<'a>>>,
+    literals: btree_set::Iter<'a, String>,
+}
+
+impl<'a> Iterator for Hosts<'a> {
+    type Item = Host<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(group) = &mut self.front {
+                if let Some(value) = group.next_value() {
+                    return Some(Host(Repr::Numbered(group.shape, value)));
+                }
+            }
+
+            match self.groups.next() {
+                Some((shape, ranges)) => self.front = Some(Group::new(shape, ranges.iter())),
+                None => break,
+            }
+        }
+
+        if let Some(group) = &mut self.back {
+            if let Some(value) = group.next_value() {
+                return Some(Host(Repr::Numbered(group.shape, value)));
+            }
+        }
+
+        self.literals.next().map(|x| Host(Repr::Literal(x)))
+    }
+
+    /// Skips hosts by counting them on the ranges, without formatting them.
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        let mut n = n as u128;
+
+        loop {
+            if let Some(group) = &mut self.front {
+                n -= group.skip_values(n);
+            }
+
+            if n == 0 {
+                return self.next();
+            }
+
+            match self.groups.next() {
+                Some((shape, ranges)) => self.front = Some(Group::new(shape, ranges.iter())),
+                None => break,
+            }
+        }
+
+        if let Some(group) = &mut self.back {
+            n -= group.skip_values(n);
+        }
+
+        if n == 0 {
+            return self.next();
+        }
+
+        self.literals
+            .nth(n as usize)
+            .map(|x| Host(Repr::Literal(x)))
+    }
+}
+
+impl DoubleEndedIterator for Hosts<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if let Some(x) = self.literals.next_back() {
+            return Some(Host(Repr::Literal(x)));
+        }
+
+        loop {
+            if let Some(group) = &mut self.back {
+                if let Some(value) = group.next_back_value() {
+                    return Some(Host(Repr::Numbered(group.shape, value)));
+                }
+            }
+
+            match self.groups.next_back() {
+                Some((shape, ranges)) => self.back = Some(Group::new(shape, ranges.iter())),
+                None => break,
+            }
+        }
+
+        let group = self.front.as_mut()?;
+
+        group
+            .next_back_value()
+            .map(|value| Host(Repr::Numbered(group.shape, value)))
+    }
+}
+
+impl<'a> IntoIterator for &'a Hostlist {
+    type Item = Host<'a>;
+    type IntoIter = Hosts<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator that moves the hosts out of a [`Hostlist`]. It can be walked from both ends.
+pub struct IntoIter {
+    groups: btree_map::IntoIter<Shape, RangeSet>,
+    front: Option<Group<Shape, std::vec::IntoIter<(u64, u64)>>>,
+    back: Option<Group<Shape, std::vec::IntoIter<(u64, u64)>>>,
+    literals: btree_set::IntoIter<String>,
+}
+
+impl Iterator for IntoIter {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(group) = &mut self.front {
+                if let Some(value) = group.next_value() {
+                    return Some(group.shape.format(value));
+                }
+            }
+
+            match self.groups.next() {
+                Some((shape, ranges)) => self.front = Some(Group::new(shape, ranges.into_iter())),
+                None => break,
+            }
+        }
+
+        if let Some(group) = &mut self.back {
+            if let Some(value) = group.next_value() {
+                return Some(group.shape.format(value));
+            }
+        }
+
+        self.literals.next()
+    }
+
+    /// Skips hosts by counting them on the ranges, without formatting them.
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        let mut n = n as u128;
+
+        loop {
+            if let Some(group) = &mut self.front {
+                n -= group.skip_values(n);
+            }
+
+            if n == 0 {
+                return self.next();
+            }
+
+            match self.groups.next() {
+                Some((shape, ranges)) => self.front = Some(Group::new(shape, ranges.into_iter())),
+                None => break,
+            }
+        }
+
+        if let Some(group) = &mut self.back {
+            n -= group.skip_values(n);
+        }
+
+        if n == 0 {
+            return self.next();
+        }
+
+        self.literals.nth(n as usize)
+    }
+}
+
+impl DoubleEndedIterator for IntoIter {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if let Some(x) = self.literals.next_back() {
+            return Some(x);
+        }
+
+        loop {
+            if let Some(group) = &mut self.back {
+                if let Some(value) = group.next_back_value() {
+                    return Some(group.shape.format(value));
+                }
+            }
+
+            match self.groups.next_back() {
+                Some((shape, ranges)) => self.back = Some(Group::new(shape, ranges.into_iter())),
+                None => break,
+            }
+        }
+
+        let group = self.front.as_mut()?;
+
+        group
+            .next_back_value()
+            .map(|value| group.shape.format(value))
+    }
+}
+
+impl IntoIterator for Hostlist {
+    type Item = String;
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            groups: Arc::unwrap_or_clone(self.groups).into_iter(),
+            front: None,
+            back: None,
+            literals: Arc::unwrap_or_clone(self.literals).into_iter(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use insta::assert_debug_snapshot;
+    use proptest::prelude::*;
+    use std::{
+        collections::{hash_map::DefaultHasher, HashMap},
+        hash::{Hash, Hasher},
+    };
+
+    fn h(x: &str) -> Hostlist {
+        Hostlist::parse(x).unwrap()
+    }
+
+    #[test]
+    fn test_shape_split() {
+        assert_debug_snapshot!([
+            Shape::split("oss01.local"),
+            Shape::split("node10"),
+            Shape::split("r1n04-ib2"),
+            Shape::split("n0"),
+            Shape::split("n00"),
+            Shape::split("5"),
+            Shape::split("mds"),
+            Shape::split("node99999999999999999999"),
+        ]);
+    }
+
+    #[test]
+    fn test_from_str() {
+        let h: Hostlist = "oss[1-2].local,mds1".parse().unwrap();
+
+        assert_eq!(
+            h.iter().collect::<Vec<_>>(),
+            vec!["mds1", "oss1.local", "oss2.local"]
+        );
+
+        let err = "oss[1-2".parse::<Hostlist>().unwrap_err();
+        assert!(err.to_string().starts_with("Parse error at 7"));
+    }
+
+    #[test]
+    fn test_parse_matches_expansion() {
+        for input in [
+            "hostname[10,11-12,002-003,5].iml.com",
+            "hostname[7-5], hostname[8,9], hostname[3,2,1]",
+            "hostname[001-999]",
+            "hostname[1,2]-[3-4]-[5,6].iml.com",
+            "hostname[1,2][3,4].iml.com",
+            "hostname[06-10],test[000-002].localdomain",
+            "hostname[01-100],hostname[010-099],hostname[098-102]",
+            "OST01[00,01],mds,[1-3]",
+            "slot[01a,2,3-4,5bc].local",
+            "node[1-3]-ib0",
+        ] {
+            let mut expected = parse(input).unwrap();
+            expected.sort();
+
+            let mut actual: Vec<_> = h(input).into_iter().collect();
+            actual.sort();
+
+            assert_eq!(actual, expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        assert_debug_snapshot!([
+            "node1,node2,node3,node4",
+            "node[1-5],node[3-8],node10",
+            "oss[08-12].local,oss[098-102].local",
+            "n[1,01,001],n[0050-0500]",
+            "mds,oss[1-2],mgs,oss[3-4]-ib0",
+            r#""a b"[1-3],"x,y"1"#,
+        ]
+        .map(|x| Hostlist::parse(x).map(|h| h.to_string())));
+
+        assert_eq!(Hostlist::new().to_string(), "");
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        for input in [
+            "hostname[10,11-12,002-003,5].iml.com",
+            "hostname[7-5], hostname[8,9], hostname[3,2,1]",
+            "hostname[001-999]",
+            "hostname[1,2]-[3-4]-[5,6].iml.com",
+            "hostname[06-10],test[000-002].localdomain",
+            "hostname[01-100],hostname[010-099],hostname[098-102]",
+            "n[0-20],n[00-20],n[000-020],n[0050-0500],n[998-1002]",
+            "OST01[00,01],mds,[1-3]",
+            "slot[01a,2,3-4,5bc].local",
+            r#"node[1-2],"weird[name]", "x,y"[1-2]"#,
+        ] {
+            let list = h(input);
+            let expr = list.to_string();
+
+            assert_eq!(
+                h(&expr).iter().collect::<Vec<_>>(),
+                list.iter().collect::<Vec<_>>(),
+                "{input} -> {expr}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_into_iter() {
+        let list = h("oss[08-09].local,mds");
+
+        let mut borrowed = vec![];
+
+        for host in &list {
+            borrowed.push(host);
+        }
+
+        assert_eq!(borrowed, vec!["oss08.local", "oss09.local", "mds"]);
+        assert_ne!(borrowed[0], "oss8.local");
+        assert_ne!(borrowed[0], "oss008.local");
+        assert_eq!(String::from(borrowed[1]), "oss09.local");
+
+        let owned: BTreeSet<String> = list.clone().into_iter().collect();
+
+        assert_eq!(
+            owned,
+            BTreeSet::from(["mds".into(), "oss08.local".into(), "oss09.local".into()])
+        );
+    }
+
+    #[test]
+    fn test_min_max_index() {
+        let list = h("node[005-080,1-3,100-200],node[500-600]-ib,mds");
+
+        assert_eq!(list.min_index("node", ""), Some((1, 0)));
+        assert_eq!(list.max_index("node", ""), Some((200, 0)));
+        assert_eq!(list.min_index("node", "-ib"), Some((500, 0)));
+        assert_eq!(h("n[001-050]").max_index("n", ""), Some((50, 3)));
+        assert_eq!(h("n[01-02],n2").max_index("n", ""), Some((2, 0)));
+        assert_eq!(list.min_index("mds", ""), None);
+        assert_eq!(list.max_index("nod", ""), None);
+    }
+
+    #[test]
+    fn test_clone_on_write() {
+        let mut a = h("node[1-10],mds");
+        let b = a.clone();
+
+        assert!(Arc::ptr_eq(&a.groups, &b.groups));
+
+        assert!(!a.remove("node11"));
+        assert!(Arc::ptr_eq(&a.groups, &b.groups));
+
+        assert!(a.remove("node5") && a.remove("mds"));
+        a.insert("oss1").unwrap();
+
+        assert_eq!(a.to_string(), "node[1-4,6-10],oss1");
+        assert_eq!(b.to_string(), "node[1-10],mds");
+
+        let c = b.clone();
+
+        assert_eq!(b.into_iter().count(), 11);
+        assert_eq!(c.len(), 11);
+    }
+
+    #[test]
+    fn test_paged() {
+        let list = h("node[1-3,7-9],oss[01-02],mds");
+        let all: Vec<_> = list.iter().map(String::from).collect();
+
+        for offset in 0..all.len() + 2 {
+            for len in 0..4 {
+                let page: Vec<_> = list.paged(offset, len).collect();
+                let expected: Vec<_> = all.iter().skip(offset).take(len).cloned().collect();
+
+                assert_eq!(page, expected, "offset {offset}, len {len}");
+            }
+        }
+
+        assert_eq!(list.len(), 9);
+    }
+
+    #[test]
+    fn test_position() {
+        let list = h("node[1-3,7-9],node[01-02],oss[1-2].local,mds,gw");
+
+        for (i, host) in list.iter().enumerate() {
+            assert_eq!(list.position(&host.to_string()), Some(i), "{host}");
+        }
+
+        assert_eq!(list.position("node4"), None);
+        assert_eq!(list.position("node001"), None);
+        assert_eq!(list.position("oss1"), None);
+        assert_eq!(list.position("login"), None);
+        assert_eq!(h("n[0-18446744073709551615],a").position("a"), None);
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        for input in [
+            "hostname[10,11-12,002-003,5].iml.com",
+            "hostname[7-5], hostname[8,9], hostname[3,2,1]",
+            "hostname[1,2]-[3-4]-[5,6].iml.com",
+            "n[0-20],n[00-20],n[000-020],n[0050-0500],n[998-1002]",
+            "OST01[00,01],mds,[1-3]",
+            "slot[01a,2,3-4,5bc].local",
+            "n[0-18446744073709551615],n[00-01]",
+            "n99999999999999999999999",
+            r#"node[1-2],"weird[name]", "x,y"[1-2],"é[1-2]""#,
+        ] {
+            let list = h(input);
+            let decoded = Hostlist::from_bytes(&list.to_bytes()).unwrap();
+
+            assert_eq!(decoded, list, "{input}");
+        }
+
+        assert_eq!(Hostlist::new().to_bytes(), vec![1, 0, 0]);
+        assert!(h("node[1-50000]").to_bytes().len() < 100);
+    }
+
+    #[test]
+    fn test_from_bytes_errors() {
+        let bytes = h("node[1-5,7],mds").to_bytes();
+
+        assert_eq!(
+            Hostlist::from_bytes(&[2, 0, 0]),
+            Err(Error::UnsupportedVersion { version: 2 })
+        );
+        assert_eq!(
+            Hostlist::from_bytes(&[]),
+            Err(Error::InvalidEncoding { offset: 0 })
+        );
+
+        for len in 1..bytes.len() {
+            assert!(
+                matches!(
+                    Hostlist::from_bytes(&bytes[..len]),
+                    Err(Error::InvalidEncoding { .. })
+                ),
+                "{len}"
+            );
+        }
+
+        let mut trailing = bytes.clone();
+
+        trailing.push(0);
+
+        assert_eq!(
+            Hostlist::from_bytes(&trailing),
+            Err(Error::InvalidEncoding {
+                offset: bytes.len()
+            })
+        );
+
+        // A prefix ending in a digit, or a host with a number stored as a literal.
+        assert_eq!(
+            Hostlist::from_bytes(&[1, 1, 1, b'1', 0, 0, 1, 0, 0, 0]),
+            Err(Error::InvalidEncoding { offset: 2 })
+        );
+        assert_eq!(
+            Hostlist::from_bytes(&[1, 0, 1, 2, b'n', b'1']),
+            Err(Error::InvalidEncoding { offset: 3 })
+        );
+        assert_eq!(
+            Hostlist::from_bytes(&[1, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f])
+                .err(),
+            Some(Error::InvalidEncoding { offset: 1 })
+        );
+
+        // Padding wider than any `u64`, or than `format!` can write.
+        for width in [
+            &[21][..],
+            &[0x80, 0x80, 0x04],
+            &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f],
+        ] {
+            let bytes = [&[1, 1, 1, b'n', 0][..], width, &[1, 1, 0, 0]].concat();
+
+            assert_eq!(
+                Hostlist::from_bytes(&bytes),
+                Err(Error::InvalidEncoding {
+                    offset: 5 + width.len()
+                }),
+                "{width:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_eq_hash() {
+        fn hash(h: &Hostlist) -> u64 {
+            let mut hasher = DefaultHasher::new();
+
+            h.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        let a = h("node[1-3,5]");
+        let b = h("node5,node[1-2],node3");
+
+        assert_eq!(a, b);
+        assert_eq!(hash(&a), hash(&b));
+        assert_ne!(h("node[01-03]"), h("node[1-3]"));
+
+        let mut c = b.clone();
+
+        c.insert("node4").unwrap();
+        assert_ne!(a, c);
+        c.remove("node4");
+        assert_eq!(a, c);
+        assert_eq!(hash(&a), hash(&c));
+
+        assert_eq!(h("n[1-4]").difference(&h("n[1-4]")), Hostlist::new());
+
+        let map: HashMap<_, _> = [(a, "a")].into_iter().collect();
+
+        assert_eq!(map.get(&b), Some(&"a"));
+    }
+
+    #[test]
+    fn test_ranges() {
+        assert_debug_snapshot!(h(
+            "oss[1-48,53,60-64].local,oss[40-50].local,oss01.local,r[1-2]n[1-4],mds"
+        )
+        .ranges());
+    }
+
+    #[test]
+    fn test_rotate() {
+        let list = h("node[1-4]");
+
+        assert_eq!(
+            list.rotate(1).collect::<Vec<_>>(),
+            vec!["node2", "node3", "node4", "node1"]
+        );
+        assert_eq!(
+            list.rotate(0).collect::<Vec<_>>(),
+            list.rotate(4).collect::<Vec<_>>()
+        );
+        assert_eq!(list.rotate(9).next(), Some("node2".into()));
+        assert_eq!(
+            h("oss[1-2],mds").rotate(2).collect::<Vec<_>>(),
+            vec!["mds", "oss1", "oss2"]
+        );
+        assert_eq!(Hostlist::new().rotate(3).count(), 0);
+
+        let list = h("node[0-99999999999]");
+
+        assert_eq!(
+            list.rotate(usize::MAX).take(2).collect::<Vec<_>>(),
+            vec!["node73709551615", "node73709551616"]
+        );
+    }
+
+    #[test]
+    fn test_len() {
+        let len = |x| h(x).len();
+
+        // Overlapping entries are counted once, unlike the product of the bracket sizes.
+        assert_eq!(len("node[1-5],node[3-8]"), 8);
+        assert_eq!(len("node[1-5],node[1-5]"), 5);
+        assert_eq!(len("node[1-3,2-4],mds,mds"), 5);
+        assert_eq!(len("r[1-2]n[1-4],r1n[3-6]"), 10);
+        // Differently padded spellings are distinct hosts.
+        assert_eq!(len("node[1-10],node[01-10]"), 19);
+        assert_eq!(len("node[0-18446744073709551615]"), 1 << 64);
+
+        assert!(Hostlist::new().is_empty());
+        assert_eq!(Hostlist::new().len(), 0);
+        assert!(!h("mds").is_empty());
+    }
+
+    #[test]
+    fn test_from_iter() {
+        let list: Hostlist = ["node3", "node1", "node2", "node01", "node02", "node10"]
+            .into_iter()
+            .collect();
+
+        assert_eq!(list.to_string(), "node[01-02,1-3,10]");
+        assert_eq!(list.len(), 6);
+
+        // Only the last run of digits is folded.
+        let list: Hostlist = ["r1n04-ib2", "r1n04-ib3", "r2n04-ib2", "r1n05-ib2"]
+            .into_iter()
+            .collect();
+
+        assert_eq!(list.to_string(), "r1n04-ib[2-3],r1n05-ib2,r2n04-ib2");
+
+        let list: Hostlist = vec![
+            "gw".to_string(),
+            "login-a".to_string(),
+            "gw".to_string(),
+            "node007".to_string(),
+            "x_1".to_string(),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(list.len(), 4);
+        assert_eq!(
+            list.iter().map(String::from).collect::<Vec<_>>(),
+            vec!["node007", "x_1", "gw", "login-a"]
+        );
+        assert_eq!(
+            h(&list.to_string())
+                .iter()
+                .map(String::from)
+                .collect::<Vec<_>>(),
+            list.iter().map(String::from).collect::<Vec<_>>()
+        );
+
+        let mut list: Hostlist = std::iter::empty::<&str>().collect();
+
+        assert!(list.is_empty());
+
+        list.extend(["n1", "n2"]);
+        assert_eq!(list.to_string(), "n[1-2]");
+    }
+
+    #[test]
+    fn test_contains() {
+        let list = h("node[1-10].a,node[3-4].b,oss[001-010],mds");
+
+        assert!(list.contains("node1.a"));
+        assert!(!list.contains("node1.b"));
+        assert!(list.contains("node3.b"));
+        assert!(list.contains("node10.a"));
+        assert!(!list.contains("node01.a"));
+        assert!(!list.contains("node11.a"));
+        assert!(list.contains("oss001"));
+        assert!(list.contains("oss010"));
+        assert!(!list.contains("oss1"));
+        assert!(!list.contains("oss0001"));
+        assert!(list.contains("mds"));
+        assert!(!list.contains("mgs"));
+        assert!(!list.contains(""));
+        assert!(h("node[0-18446744073709551615]").contains("node18446744073709551615"));
+    }
+
+    #[test]
+    fn test_union() {
+        let x = h("node[1-5]").union(&h("node[4-9]"));
+
+        assert_eq!(x.groups.len(), 1);
+        assert_eq!(
+            x.groups.values().next().unwrap().iter().collect::<Vec<_>>(),
+            vec![(1, 9)]
+        );
+        assert_eq!(x.len(), 9);
+        assert_eq!(x.to_string(), "node[1-9]");
+
+        let x = h("node[1-5],mds").union(&h("node05,mds,mgs"));
+
+        assert_eq!(x.len(), 8);
+        assert_eq!(x.to_string(), "node[1-5,05],mds,mgs");
+
+        assert_eq!(h("a[1-2]").union(&Hostlist::new()).to_string(), "a[1-2]");
+    }
+
+    #[test]
+    fn test_intersection() {
+        let x = h("oss[1-100].local").intersection(&h("oss[90-200].local"));
+
+        assert_eq!(
+            x.groups.values().next().unwrap().iter().collect::<Vec<_>>(),
+            vec![(90, 100)]
+        );
+        assert_eq!(x.to_string(), "oss[90-100].local");
+
+        let x = h("node[1-5],node[01-05],mds,mgs").intersection(&h("node[04-09],mgs,oss1"));
+
+        assert_eq!(x.to_string(), "node[04-05],mgs");
+        assert!(h("node[1-5]").intersection(&h("node[6-9]")).is_empty());
+        assert!(h("node5").intersection(&h("node05")).is_empty());
+    }
+
+    #[test]
+    fn test_is_disjoint() {
+        assert!(!h("oss[1-100].local").is_disjoint(&h("mds1,oss[90-200].local")));
+        assert!(!h("mgs,node1").is_disjoint(&h("mgs")));
+        assert!(h("node[1-5]").is_disjoint(&h("node[6-9]")));
+        assert!(h("node5").is_disjoint(&h("node05")));
+        assert!(h("node1").is_disjoint(&Hostlist::new()));
+    }
+
+    #[test]
+    fn test_difference() {
+        let x = h("node[1-100]").difference(&h("node[40-45]"));
+
+        assert_eq!(
+            x.groups.values().next().unwrap().iter().collect::<Vec<_>>(),
+            vec![(1, 39), (46, 100)]
+        );
+        assert_eq!(x.to_string(), "node[1-39,46-100]");
+
+        assert_eq!(
+            h("node[1-5],node[01-05],mds")
+                .difference(&h("node[3-9],oss1,mds"))
+                .to_string(),
+            "node[1-2,01-05]"
+        );
+
+        let x = h("node[1-5]").difference(&h("node[1-5]"));
+
+        assert!(x.is_empty());
+        assert_eq!(x.to_string(), "");
+    }
+
+    #[test]
+    fn test_filter() {
+        let x = h("node[1-10],mds").filter(|x| x != "node4" && x != "node7");
+
+        assert_eq!(
+            x.groups.values().next().unwrap().iter().collect::<Vec<_>>(),
+            vec![(1, 3), (5, 6), (8, 10)]
+        );
+        assert_eq!(x.len(), 9);
+        assert!(x.contains("mds") && !x.contains("node7"));
+        assert_eq!(x, h("node[1-3,5-6,8-10],mds"));
+
+        let mut seen = vec![];
+        let x = h("n[01-03],oss").filter(|x| {
+            seen.push(x.to_string());
+
+            x.ends_with('2')
+        });
+
+        assert_eq!(seen, vec!["n01", "n02", "n03", "oss"]);
+        assert_eq!(x.to_string(), "n02");
+
+        let x = h("node[1-5],mds").filter(|_| false);
+
+        assert!(x.is_empty());
+        assert_eq!(x, Hostlist::new());
+        assert_eq!(x.to_string(), "");
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut x = h("node[1-10],mds,oss");
+        let copy = x.clone();
+
+        x.retain(|x| x != "node5" && x != "oss");
+
+        assert_eq!(x, h("node[1-4,6-10],mds"));
+        assert_eq!(copy.len(), 12);
+
+        x.retain(|x| x.ends_with(['1', '3', '7', '9']));
+
+        assert_eq!(x.run_count(), 4);
+        assert_eq!(x.to_string(), "node[1,3,7,9]");
+        assert!(x.contains("node7") && !x.contains("node8"));
+
+        x.retain(|_| false);
+
+        assert!(x.is_empty());
+        assert_eq!(x, Hostlist::new());
+    }
+
+    #[test]
+    fn test_pop() {
+        let mut x = h("node[1-3,5],mds");
+        let copy = x.clone();
+
+        assert_eq!(x.pop_first().as_deref(), Some("node1"));
+        assert_eq!(x.pop_last().as_deref(), Some("mds"));
+        assert_eq!(x.pop_last().as_deref(), Some("node5"));
+        assert_eq!(x.len(), 2);
+        assert_eq!(x.to_string(), "node[2-3]");
+        assert_eq!(copy.len(), 5);
+
+        assert_eq!(x.pop_last().as_deref(), Some("node3"));
+        assert_eq!(x.pop_first().as_deref(), Some("node2"));
+        assert!(x.is_empty());
+        assert_eq!(x, Hostlist::new());
+        assert_eq!(x.pop_first(), None);
+        assert_eq!(x.pop_last(), None);
+
+        let mut x = h("n[18446744073709551614-18446744073709551615]");
+
+        assert_eq!(x.pop_last().as_deref(), Some("n18446744073709551615"));
+        assert_eq!(x.len(), 1);
+    }
+
+    #[test]
+    fn test_symmetric_difference() {
+        assert_eq!(
+            h("node[1-10],mds")
+                .symmetric_difference(&h("node[5-15],mgs"))
+                .to_string(),
+            "node[1-4,11-15],mds,mgs"
+        );
+        assert!(h("node[1-3]")
+            .symmetric_difference(&h("node[1-3]"))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_insert_remove() {
+        let mut list = h("node[1-4],mds");
+
+        assert_eq!(list.insert("node5"), Ok(true));
+        assert_eq!(list.insert("node5"), Ok(false));
+        assert_eq!(list.insert("node05"), Ok(true));
+        assert_eq!(list.insert("mgs"), Ok(true));
+        assert_eq!(list.to_string(), "node[1-5,05],mds,mgs");
+        assert_eq!(list.groups.len(), 2);
+
+        assert!(list.remove("node3"));
+        assert!(!list.remove("node3"));
+        assert!(list.remove("node05"));
+        assert!(list.remove("mds"));
+        assert!(!list.remove("oss1"));
+        assert_eq!(list.to_string(), "node[1-2,4-5],mgs");
+        assert_eq!(list.groups.len(), 1);
+
+        assert_debug_snapshot!(list.insert("node[6-7]"));
+        assert!(list.insert("a,b").is_err());
+        assert!(list.insert("").is_err());
+        assert_eq!(list.len(), 5);
+    }
+
+    #[test]
+    fn test_large_range_is_not_expanded() {
+        let list = h("node[0-999999999999]");
+
+        assert_eq!(list.groups.len(), 1);
+        assert_eq!(list.iter().nth(10).unwrap(), "node10");
+    }
+
+    #[test]
+    fn test_expansion_limit() {
+        let list = h("r[1-3]n[1-2],n[0-9]-ib0");
+
+        assert_eq!(list.len(), 16);
+        assert!(list.contains("r2n1") && list.contains("n7-ib0"));
+
+        assert_eq!(
+            Hostlist::parse("n[0-18446744073709551615]-ib0"),
+            Err(Error::TooManyHosts {
+                count: 1 << 64,
+                limit: Hostlist::EXPANSION_LIMIT
+            })
+        );
+        assert_eq!(
+            Hostlist::parse("r[1-1048576]n1,n[1-2]-ib0,mds1"),
+            Err(Error::TooManyHosts {
+                count: 1048578,
+                limit: Hostlist::EXPANSION_LIMIT
+            })
+        );
+        assert_eq!(
+            Hostlist::parse("n[1-2000000]-ib0").unwrap_err().to_string(),
+            "Expression expands to 2000000 hosts, which exceeds the limit of 1048576"
+        );
+        assert!(Hostlist::parse("node[0-18446744073709551615]-ib").is_ok());
+    }
+
+    /// Small expressions over a couple of prefixes and paddings, so generated lists overlap.
+    fn expression() -> impl Strategy<Value = String> {
+        let entry = (
+            prop::sample::select(vec!["n", "n-", "m"]),
+            prop::sample::select(vec![0usize, 2, 3]),
+            0u64..40,
+            0u64..10,
+            prop::sample::select(vec!["", ".ib"]),
+        )
+            .prop_map(|(prefix, width, lo, len, suffix)| {
+                format!("{prefix}[{lo:0>width$}-{:0>width$}]{suffix}", lo + len)
+            });
+
+        (
+            prop::collection::vec(entry, 1..4),
+            prop::collection::vec(prop::sample::select(vec!["mds", "mgs"]), 0..2),
+        )
+            .prop_map(|(xs, ys)| {
+                xs.into_iter()
+                    .chain(ys.into_iter().map(String::from))
+                    .join(",")
+            })
+    }
+
+    fn hosts(h: &Hostlist) -> BTreeSet<String> {
+        h.iter().map(String::from).collect()
+    }
+
+    proptest! {
+        #[test]
+        fn prop_set_operations(a in expression(), b in expression()) {
+            let (x, y) = (h(&a), h(&b));
+            let (xs, ys) = (hosts(&x), hosts(&y));
+
+            prop_assert_eq!(hosts(&x.union(&y)), &xs | &ys);
+            prop_assert_eq!(hosts(&x.intersection(&y)), &xs & &ys);
+            prop_assert_eq!(hosts(&x.difference(&y)), &xs - &ys);
+            prop_assert_eq!(hosts(&x.symmetric_difference(&y)), &xs ^ &ys);
+            prop_assert_eq!(
+                hosts(&x.symmetric_difference(&y)),
+                hosts(&x.union(&y).difference(&x.intersection(&y)))
+            );
+            prop_assert_eq!(x.symmetric_difference(&y).len(), (&xs ^ &ys).len() as u128);
+        }
+
+        #[test]
+        fn prop_eq_matches_hosts(a in expression(), b in expression()) {
+            let (x, y) = (h(&a), h(&b));
+
+            prop_assert_eq!(x == y, hosts(&x) == hosts(&y));
+            prop_assert_eq!(x.union(&y), y.union(&x));
+        }
+
+        #[test]
+        fn prop_iter_both_ends(
+            a in expression(),
+            ends in prop::collection::vec(any::<bool>(), 0..64),
+        ) {
+            let list = h(&format!("{a},mds,oss"));
+            let mut hosts = list.iter();
+            let (mut front, mut back) = (vec![], vec![]);
+
+            for from_back in ends {
+                if from_back {
+                    back.extend(hosts.next_back().map(String::from));
+                } else {
+                    front.extend(hosts.next().map(String::from));
+                }
+            }
+
+            front.extend(hosts.map(String::from));
+            front.extend(back.into_iter().rev());
+
+            prop_assert_eq!(&front, &list.iter().map(String::from).collect::<Vec<_>>());
+
+            let mut rev: Vec<_> = list.clone().into_iter().rev().collect();
+
+            rev.reverse();
+            prop_assert_eq!(rev, front);
+        }
+
+        #[test]
+        fn prop_iter_nth(
+            a in expression(),
+            steps in prop::collection::vec((0..40usize, any::<bool>()), 0..16),
+        ) {
+            let list = h(&format!("{a},mds,oss"));
+            let mut model: std::collections::VecDeque<String> =
+                list.iter().map(String::from).collect();
+            let (mut hosts, mut into) = (list.iter(), list.clone().into_iter());
+
+            for (n, from_back) in steps {
+                let expected = if from_back {
+                    model.pop_back()
+                } else {
+                    model.drain(..n.min(model.len()));
+                    model.pop_front()
+                };
+
+                if from_back {
+                    prop_assert_eq!(hosts.next_back().map(String::from), expected.clone());
+                    prop_assert_eq!(into.next_back(), expected);
+                } else {
+                    prop_assert_eq!(hosts.nth(n).map(String::from), expected.clone());
+                    prop_assert_eq!(into.nth(n), expected);
+                }
+            }
+        }
+
+        #[test]
+        fn prop_bytes_round_trip(xs in crate::ast::arbitrary::hostlists()) {
+            let list = h(&xs.iter().join(","));
+
+            prop_assert_eq!(Hostlist::from_bytes(&list.to_bytes()).unwrap(), list);
+        }
+    }
+}
@@ -0,0 +1,388 @@
+// Copyright (c) 2022 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+use crate::{
+    structures::{cardinality, first_index, host_at_with, Numbers, Part, RangeOutput},
+    Hostlist,
+};
+use std::{cell::OnceCell, collections::HashSet};
+
+/// Lazily expands parsed hostlists in the same order as [`crate::parse`], without deduplication.
+///
+/// Hosts are computed from their index, so nothing is generated ahead of the caller, from either
+/// end. The hosts left are those from `front` in `segment` up to, but not including, `back` in
+/// `back_segment`.
+pub(crate) struct Expansion {
+    hosts: Vec<(Vec<Part>, u128)>,
+    segment: usize,
+    front: u128,
+    back_segment: usize,
+    back: u128,
+    numbers: Numbers,
+}
+
+impl Expansion {
+    pub(crate) fn new(hosts: Vec<Vec<Part>>) -> Self {
+        let hosts: Vec<_> = hosts
+            .into_iter()
+            .map(|parts| {
+                let count = cardinality(&parts);
+
+                (parts, count)
+            })
+            .collect();
+
+        Self {
+            back_segment: hosts.len(),
+            hosts,
+            segment: 0,
+            front: 0,
+            back: 0,
+            numbers: Numbers::DECIMAL,
+        }
+    }
+
+    /// Writes numbers as `numbers` instead of unsigned decimal.
+    pub(crate) fn with_numbers(mut self, numbers: Numbers) -> Self {
+        self.numbers = numbers;
+        self
+    }
+
+    /// The number of hosts left to generate, including duplicates.
+    pub(crate) fn remaining(&self) -> u128 {
+        self.hosts[self.segment..self.back_segment]
+            .iter()
+            .fold(self.back, |acc, (_, count)| acc.saturating_add(*count))
+            - self.front
+    }
+
+    fn is_done(&self) -> bool {
+        (self.segment, self.front) >= (self.back_segment, self.back)
+    }
+
+    /// The index of the entry the next host comes from, moving past entries already used up.
+    fn front_entry(&mut self) -> Option<usize> {
+        loop {
+            if self.is_done() {
+                return None;
+            }
+
+            if self.front < self.hosts[self.segment].1 {
+                return Some(self.segment);
+            }
+
+            self.segment += 1;
+            self.front = 0;
+        }
+    }
+
+    /// Skips up to `n` hosts of the entry at the front, without generating them, and returns how
+    /// many were skipped.
+    fn skip_in_entry(&mut self, n: u128) -> u128 {
+        let end = if self.segment == self.back_segment {
+            self.back
+        } else {
+            self.hosts.get(self.segment).map_or(0, |(_, count)| *count)
+        };
+        let skipped = n.min(end.saturating_sub(self.front));
+
+        self.front += skipped;
+
+        skipped
+    }
+
+    /// The next host, with the index of its entry and its index in the entry.
+    fn next_indexed(&mut self) -> Option<(usize, u128, String)> {
+        let segment = self.front_entry()?;
+        let index = self.front;
+        let host = host_at_with(&self.hosts[segment].0, index, self.numbers);
+
+        self.front += 1;
+
+        Some((segment, index, host))
+    }
+
+    /// The last host left, with the index of its entry and its index in the entry.
+    fn next_back_indexed(&mut self) -> Option<(usize, u128, String)> {
+        loop {
+            if self.is_done() {
+                return None;
+            }
+
+            if self.back > 0 {
+                self.back -= 1;
+
+                let host = host_at_with(&self.hosts[self.back_segment].0, self.back, self.numbers);
+
+                return Some((self.back_segment, self.back, host));
+            }
+
+            self.back_segment -= 1;
+            self.back = self.hosts[self.back_segment].1;
+        }
+    }
+}
+
+impl Iterator for Expansion {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_indexed().map(|(_, _, host)| host)
+    }
+
+    /// Skips whole entries, and then hosts within an entry, by counting them.
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        let mut n = n as u128;
+
+        while n > 0 {
+            self.front_entry()?;
+            n -= self.skip_in_entry(n);
+        }
+
+        self.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.remaining();
+
+        (
+            usize::try_from(remaining).unwrap_or(usize::MAX),
+            usize::try_from(remaining).ok(),
+        )
+    }
+}
+
+impl DoubleEndedIterator for Expansion {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.next_back_indexed().map(|(_, _, host)| host)
+    }
+}
+
+/// A lazy iterator over the hosts of an expression, returned by [`crate::parse_iter`].
+///
+/// Hosts come in the same order as [`crate::parse`], and repeats are dropped unless
+/// [`HostlistIter::keep_duplicates`] is used. Going forwards, only the hosts already yielded are
+/// remembered.
+///
+/// It can also be walked from the back, and from both ends at once, giving the same hosts as going
+/// forwards, and [`Iterator::nth`] and [`Iterator::skip`] count hosts on the ranges rather than
+/// generating them. Once either is used, a host is instead told to be a repeat by matching it
+/// against the entries before its own, and against the hosts before it in its entry, without
+/// expanding them. Entries are only folded into ranges when hosts are skipped, to tell whether
+/// they hold any repeat. An entry such as `n[1-100]-ib0`, whose number is not its last run of
+/// digits, is folded one host at a time, so it is only folded when skipping over more hosts than
+/// that; otherwise the hosts skipped are checked one at a time.
+///
+/// [`Iterator::size_hint`] is counted from the ranges: the number of hosts left to generate,
+/// repeats included. Without deduplication that count is exact, so `collect` allocates once. With
+/// it, the count is only an upper bound and the lower bound is 0, as every host left may be a
+/// repeat.
+pub struct HostlistIter {
+    hosts: Expansion,
+    seen: Seen,
+}
+
+/// How [`HostlistIter`] tells repeats.
+enum Seen {
+    /// Repeats are kept.
+    Nothing,
+    /// The hosts yielded so far, while only walking forwards one host at a time.
+    Hosts(HashSet<String>),
+    /// Repeats are told from the entries, once hosts are skipped or taken from the back.
+    Entries(Entries),
+}
+
+/// Tells whether a host is the first of its kind from its position alone.
+struct Entries {
+    /// Each entry's hosts, once folded into ranges.
+    folded: Vec<OnceCell<Hostlist>>,
+    /// Whether each entry's hosts are all the first of their kind, when known.
+    distinct: Vec<OnceCell<bool>>,
+}
+
+impl Seen {
+    /// Switches to telling repeats by their position, which no longer needs the hosts yielded.
+    fn entries(&mut self, hosts: &Expansion) -> Option<&mut Entries> {
+        if let Seen::Hosts(_) = self {
+            *self = Seen::Entries(Entries::new(hosts));
+        }
+
+        match self {
+            Seen::Entries(x) => Some(x),
+            _ => None,
+        }
+    }
+}
+
+impl Entries {
+    fn new(hosts: &Expansion) -> Self {
+        Self {
+            folded: (0..hosts.hosts.len()).map(|_| OnceCell::new()).collect(),
+            distinct: (0..hosts.hosts.len()).map(|_| OnceCell::new()).collect(),
+        }
+    }
+
+    /// The hosts of the entry as ranges, if folding them adds no more than `limit` hosts one at
+    /// a time. A fold that would is left for a later, longer skip.
+    fn folded(&self, hosts: &Expansion, segment: usize, limit: u128) -> Option<&Hostlist> {
+        let folded = &self.folded[segment];
+
+        if folded.get().is_none() {
+            if let Some(x) = Hostlist::fold(&hosts.hosts[segment].0, limit) {
+                let _ = folded.set(x);
+            }
+        }
+
+        folded.get()
+    }
+
+    fn is_first(&self, hosts: &Expansion, segment: usize, index: u128, host: &str) -> bool {
+        let first = |parts| first_index(parts, host, hosts.numbers);
+
+        hosts.hosts[..segment]
+            .iter()
+            .all(|(parts, _)| first(parts).is_none())
+            && first(&hosts.hosts[segment].0) == Some(index)
+    }
+
+    /// Whether the hosts of the entry are all the first of their kind, so they can be counted
+    /// without checking each one. Finding out may fold it and the entries before it, each adding
+    /// no more than `limit` hosts one at a time; when that is not enough, this is `false` for now.
+    fn is_distinct(&self, hosts: &Expansion, segment: usize, limit: u128) -> bool {
+        if let Some(distinct) = self.distinct[segment].get() {
+            return *distinct;
+        }
+
+        let (parts, count) = &hosts.hosts[segment];
+
+        // The first entry has nothing to repeat but its own hosts, which a single range never does.
+        if segment == 0 && is_single_range(parts) {
+            return *self.distinct[segment].get_or_init(|| true);
+        }
+
+        let Some(x) = self.folded(hosts, segment, limit) else {
+            return false;
+        };
+        let mut distinct = x.len() == *count;
+
+        for i in 0..segment {
+            if !distinct {
+                break;
+            }
+
+            let Some(y) = self.folded(hosts, i, limit) else {
+                return false;
+            };
+
+            distinct = y.is_disjoint(x);
+        }
+
+        *self.distinct[segment].get_or_init(|| distinct)
+    }
+}
+
+/// Whether a single hostlist has at most one bracket group, holding a single range, stepped or
+/// not, so that its hosts are all different.
+fn is_single_range(parts: &[Part]) -> bool {
+    let mut groups = parts.iter().filter_map(Part::get_ranges);
+
+    matches!(
+        (groups.next().map(Vec::as_slice), groups.next()),
+        (
+            None | Some([RangeOutput::Range(..)
+                | RangeOutput::RangeReversed(..)
+                | RangeOutput::Stepped(..)]),
+            None
+        )
+    )
+}
+
+impl HostlistIter {
+    pub(crate) fn new(hosts: Expansion) -> Self {
+        Self {
+            hosts,
+            seen: Seen::Hosts(HashSet::new()),
+        }
+    }
+
+    /// Yields every generated host, repeats included, which makes the size hint exact.
+    pub fn keep_duplicates(mut self) -> Self {
+        self.seen = Seen::Nothing;
+        self
+    }
+}
+
+impl Iterator for HostlistIter {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.seen {
+            Seen::Nothing => self.hosts.next(),
+            Seen::Hosts(seen) => self.hosts.find(|x| seen.insert(x.clone())),
+            Seen::Entries(entries) => loop {
+                let (segment, index, host) = self.hosts.next_indexed()?;
+
+                if entries.is_first(&self.hosts, segment, index, &host) {
+                    return Some(host);
+                }
+            },
+        }
+    }
+
+    /// Skips the hosts of entries without repeats by counting them, and checks the others one at
+    /// a time.
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        if n == 0 {
+            return self.next();
+        }
+
+        let Some(entries) = self.seen.entries(&self.hosts) else {
+            return self.hosts.nth(n);
+        };
+        let mut n = n as u128;
+
+        while n > 0 {
+            let segment = self.hosts.front_entry()?;
+
+            // Folding costs no more than checking the hosts skipped.
+            if entries.is_distinct(&self.hosts, segment, n) {
+                n -= self.hosts.skip_in_entry(n);
+                continue;
+            }
+
+            while n > 0 && self.hosts.front_entry() == Some(segment) {
+                let (segment, index, host) = self.hosts.next_indexed()?;
+
+                n -= u128::from(entries.is_first(&self.hosts, segment, index, &host));
+            }
+        }
+
+        self.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.hosts.size_hint();
+
+        match self.seen {
+            Seen::Nothing => (lower, upper),
+            _ => (0, upper),
+        }
+    }
+}
+
+impl DoubleEndedIterator for HostlistIter {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let Some(entries) = self.seen.entries(&self.hosts) else {
+            return self.hosts.next_back();
+        };
+
+        loop {
+            let (segment, index, host) = self.hosts.next_back_indexed()?;
+
+            if entries.is_first(&self.hosts, segment, index, &host) {
+                return Some(host);
+            }
+        }
+    }
+}
@@ -0,0 +1,75 @@
+// Copyright (c) 2022 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+use crate::{parse_hostlists, Error, Expansion};
+use std::{collections::HashSet, fmt::Write as _};
+
+/// Parses `input` like [`crate::parse`], and writes the hosts as a JSON array of strings.
+///
+/// The array is built while the hosts are generated, without collecting them first. The whole
+/// expression is parsed before anything is written, so an error never comes with partial output.
+///
+/// ```
+/// use hostlist_parser::parse_to_json;
+///
+/// assert_eq!(parse_to_json("oss[1-2],mds1").unwrap(), r#"["oss1","oss2","mds1"]"#);
+/// ```
+pub fn parse_to_json(input: &str) -> Result<String, Error> {
+    let hosts = parse_hostlists(input)?;
+    let mut seen = HashSet::new();
+    let mut json = String::from("[");
+
+    for (i, host) in Expansion::new(hosts)
+        .filter(|x| seen.insert(x.clone()))
+        .enumerate()
+    {
+        if i > 0 {
+            json.push(',');
+        }
+
+        write_str(&mut json, &host);
+    }
+
+    json.push(']');
+
+    Ok(json)
+}
+
+/// Writes `x` as a JSON string literal.
+fn write_str(json: &mut String, x: &str) {
+    json.push('"');
+
+    for c in x.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+
+    json.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use insta::assert_debug_snapshot;
+
+    #[test]
+    fn test_parse_to_json() {
+        assert_eq!(
+            parse_to_json("node[1-3],node2").unwrap(),
+            r#"["node1","node2","node3"]"#
+        );
+        assert_eq!(parse_to_json(r#""a\"b\\c""#).unwrap(), r#"["a\"b\\c"]"#);
+        assert_eq!(parse_to_json("\"a\tb\u{1}\"").unwrap(), r#"["a\tb\u0001"]"#);
+        assert_debug_snapshot!(parse_to_json("node[1-"));
+    }
+}
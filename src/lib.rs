@@ -2,24 +2,74 @@
 // Use of this source code is governed by a MIT-style
 // license that can be found in the LICENSE file.
 
+pub mod ast;
+mod builder;
+mod error;
+mod filter;
+mod fold;
+mod groups;
+mod hostlist;
+mod iter;
+#[cfg(feature = "json")]
+mod json;
+mod lint;
+#[cfg(feature = "net")]
+mod net;
+mod options;
+mod overlap;
+mod parts;
+mod range_set;
+mod read;
+mod sort;
 mod structures;
-
-use crate::structures::{flatten_ranges, Part, RangeOutput};
+mod template;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+pub use crate::ast::parse_ast;
+pub use crate::builder::HostlistBuilder;
+pub use crate::error::Error;
+pub use crate::filter::parse_filtered;
+#[cfg(feature = "regex")]
+pub use crate::filter::parse_filtered_regex;
+pub use crate::fold::fold_nd;
+pub use crate::groups::parse_with_groups;
+pub use crate::hostlist::{Host, Hostlist, Hosts, IntoIter, PrefixRanges};
+use crate::iter::Expansion;
+pub use crate::iter::HostlistIter;
+#[cfg(feature = "json")]
+pub use crate::json::parse_to_json;
+pub use crate::lint::{parse_lint, Lint};
+#[cfg(feature = "net")]
+pub use crate::net::{parse_socket_addrs, ResolveFailure};
+pub use crate::options::{Dedup, FoldOptions, ParseOptions, ReadOptions};
+pub use crate::overlap::{parse_overlaps, Overlap};
+pub use crate::parts::{parse_parts, ExpandedHost, Segment};
+pub use crate::read::parse_read;
+pub use crate::sort::{cmp_hosts, sort_hosts};
+use crate::structures::{bounds, cardinality, Numbers, Part, RangeOutput, Syntax};
+pub use crate::template::{expand_template, map_template};
+#[cfg(feature = "wasm")]
+pub use crate::wasm::parse_js;
 use combine::{
-    attempt, between, choice, eof,
+    any, attempt, between, choice, eof,
     error::{ParseError, StreamError},
-    many1, not_followed_by, optional,
+    look_ahead, many, many1, none_of, not_followed_by, optional,
     parser::{
-        char::{alpha_num, digit, spaces},
+        char::{alpha_num, digit, letter, spaces, string},
         combinator::ignore,
         repeat::repeat_until,
         EasyParser,
     },
-    sep_by1,
+    satisfy, sep_by1,
     stream::{Stream, StreamErrorFor},
-    token, Parser,
+    token, value, Parser,
 };
 use itertools::Itertools as _;
+use std::collections::{HashMap, HashSet};
+
+/// The error returned when an expression fails to parse, positioned by byte offset.
+pub type ParseErrors<'a> = combine::stream::easy::Errors<char, &'a str, usize>;
 
 fn comma<I>() -> impl Parser<I, Output = char>
 where
@@ -69,185 +119,1103 @@ where
     many1(alpha_num().or(dash()).or(token('.')))
 }
 
-fn digits<I>() -> impl Parser<I, Output = String>
+/// A double-quoted host, emitted verbatim. `\` escapes the next character.
+fn quoted<I>() -> impl Parser<I, Output = String>
+where
+    I: Stream<Token = char>,
+    I::Error: ParseError<I::Token, I::Range, I::Position>,
+{
+    let escaped = token('\\').with(any());
+
+    between(
+        token('"'),
+        token('"'),
+        many1(escaped.or(none_of(['"', '\\']))),
+    )
+}
+
+/// A run of digits. Any decimal digit is taken whatever the `radix`, so that one out of range is
+/// reported as an invalid number rather than cutting the run short.
+fn digits<I>(radix: u32) -> impl Parser<I, Output = String>
+where
+    I: Stream<Token = char>,
+    I::Error: ParseError<I::Token, I::Range, I::Position>,
+{
+    many1(digit().or(satisfy(move |c: char| radix == 16 && c.is_ascii_hexdigit())))
+}
+
+/// The digits of a number, and whether it has a `-` sign. The sign is only taken from signed
+/// `numbers`.
+fn signed_digits<I>(numbers: Numbers) -> impl Parser<I, Output = (bool, String)>
 where
     I: Stream<Token = char>,
     I::Error: ParseError<I::Token, I::Range, I::Position>,
 {
-    many1(digit())
+    if numbers.signed {
+        optional(dash())
+            .map(|x| x.is_some())
+            .and(digits(numbers.radix))
+            .left()
+    } else {
+        digits(numbers.radix).map(|x| (false, x)).right()
+    }
 }
 
-fn leading_zeros<I>() -> impl Parser<I, Output = (usize, u64)>
+fn leading_zeros<I>(numbers: Numbers) -> impl Parser<I, Output = (usize, u64)>
 where
     I: Stream<Token = char>,
     I::Error: ParseError<I::Token, I::Range, I::Position>,
 {
-    digits().and_then(|x| {
+    signed_digits(numbers).and_then(move |(negative, x)| {
         let mut digits = x.chars().take_while(|x| x == &'0').count();
 
         if x.len() == digits {
             digits -= 1;
         }
 
-        x.parse::<u64>()
+        numbers
+            .parse(negative, &x)
             .map(|num| (digits, num))
             .map_err(StreamErrorFor::<I>::other)
     })
 }
 
-fn range_digits<I>() -> impl Parser<I, Output = RangeOutput>
+/// The operator between the two ends of a range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RangeSep {
+    /// `1-5`, inclusive.
+    Dash,
+    /// `1..=5`, inclusive.
+    Inclusive,
+    /// `1..5`, excluding the end.
+    Exclusive,
+}
+
+fn range_sep<I>(syntax: Syntax) -> impl Parser<I, Output = RangeSep>
+where
+    I: Stream<Token = char>,
+    I::Error: ParseError<I::Token, I::Range, I::Position>,
+{
+    let dash = token(syntax.range_sep).map(|_| RangeSep::Dash);
+    let dots = token('.')
+        .with(token('.'))
+        .with(optional(token('=')))
+        .map(|eq| match eq {
+            Some(_) => RangeSep::Inclusive,
+            None => RangeSep::Exclusive,
+        });
+
+    match syntax.range_word {
+        Some(word) => dash
+            .or(attempt(string(word).skip(not_followed_by(letter()))).map(|_| RangeSep::Dash))
+            .or(dots)
+            .left(),
+        None => dash.or(dots).right(),
+    }
+}
+
+fn range_digits_sep<I>(syntax: Syntax) -> impl Parser<I, Output = (RangeSep, RangeOutput)>
 where
     I: Stream<Token = char>,
     I::Error: ParseError<I::Token, I::Range, I::Position>,
 {
+    let numbers = syntax.numbers;
+
+    // The end value is only peeked at inside `attempt`, then parsed again so that a padding
+    // mismatch is reported at the position of the end value.
     attempt((
-        leading_zeros(),
-        optional_spaces().with(dash()),
-        optional_spaces().with(leading_zeros()),
+        leading_zeros(numbers),
+        optional_spaces().with(range_sep(syntax)),
+        optional_spaces().with(look_ahead(signed_digits(numbers))),
     ))
-    .and_then(|((start_zeros, start), _, (end_zeros, end))| {
-        let mut xs = [start, end];
-        xs.sort_unstable();
-
-        let same_prefix_len = start_zeros == end_zeros;
+    .then(move |((start_zeros, start), sep, _)| {
+        leading_zeros(numbers).and_then(move |(end_zeros, end)| {
+            let same_prefix_len = start_zeros == end_zeros;
+
+            // With different padding on each end, every value is padded to the written width of
+            // the smaller end, so `098-102` keeps three digits throughout.
+            let prefix = |zeros, num| {
+                if same_prefix_len {
+                    zeros
+                } else {
+                    zeros + numbers.num_digits(num) - 1
+                }
+            };
+            let (start_prefix, end_prefix) = (prefix(start_zeros, start), prefix(end_zeros, end));
+
+            if (start > end && start_zeros > end_zeros) || (start <= end && end_zeros > start_zeros)
+            {
+                return Err(StreamErrorFor::<I>::unexpected_static_message(
+                    "larger end padding",
+                ));
+            }
 
-        let (range, start_zeros, end_zeros) = if start > end {
-            (
-                RangeOutput::RangeReversed(end_zeros, same_prefix_len, end, start),
-                end_zeros,
-                start_zeros,
-            )
-        } else {
-            (
-                RangeOutput::Range(start_zeros, same_prefix_len, start, end),
-                start_zeros,
-                end_zeros,
-            )
-        };
+            let end = match sep {
+                RangeSep::Exclusive if start == end => {
+                    return Err(StreamErrorFor::<I>::unexpected_static_message(
+                        "empty exclusive range",
+                    ))
+                }
+                RangeSep::Exclusive if start < end => end - 1,
+                RangeSep::Exclusive => end + 1,
+                RangeSep::Dash | RangeSep::Inclusive => end,
+            };
+
+            let range = if start > end {
+                RangeOutput::RangeReversed(end_prefix, same_prefix_len, end, start)
+            } else {
+                RangeOutput::Range(start_prefix, same_prefix_len, start, end)
+            };
+
+            Ok((sep, range))
+        })
+    })
+    .and(optional(
+        optional_spaces().with(token(syntax.step_sep)).with(step()),
+    ))
+    .map(|((sep, range), step)| match step {
+        Some(step) if step > 1 => (sep, RangeOutput::Stepped(Box::new(range), step)),
+        _ => (sep, range),
+    })
+}
 
-        if end_zeros > start_zeros {
-            Err(StreamErrorFor::<I>::unexpected_static_message(
-                "larger end padding",
-            ))
-        } else {
-            Ok(range)
+/// The step of a range, as in `[4-10:2]`. It is a count, so always decimal.
+fn step<I>() -> impl Parser<I, Output = u64>
+where
+    I: Stream<Token = char>,
+    I::Error: ParseError<I::Token, I::Range, I::Position>,
+{
+    optional_spaces().with(digits(10)).and_then(|x| {
+        match x.parse::<u64>().map_err(StreamErrorFor::<I>::other)? {
+            0 => Err(StreamErrorFor::<I>::unexpected_static_message("zero step")),
+            step => Ok(step),
         }
     })
 }
 
-fn disjoint_digits<I>() -> impl Parser<I, Output = RangeOutput>
+#[cfg(test)]
+fn range_digits<I>() -> impl Parser<I, Output = RangeOutput>
+where
+    I: Stream<Token = char>,
+    I::Error: ParseError<I::Token, I::Range, I::Position>,
+{
+    range_digits_sep(Syntax::DEFAULT).map(|(_, range)| range)
+}
+
+fn disjoint_digits<I>(syntax: Syntax) -> impl Parser<I, Output = RangeOutput>
 where
     I: Stream<Token = char>,
     I::Error: ParseError<I::Token, I::Range, I::Position>,
 {
+    let numbers = syntax.numbers;
     let not_name = not_followed_by(
         optional_spaces()
-            .with(digits())
+            .with(signed_digits(numbers))
             .skip(optional_spaces())
-            .skip(dash())
+            .skip(range_sep(syntax))
             .map(|_| ""),
     );
 
+    let member = (leading_zeros(numbers), optional(many1(letter())))
+        .map(|((prefix, num), suffix)| (prefix, num, suffix));
+
     sep_by1(
-        optional_spaces()
-            .with(leading_zeros())
-            .skip(optional_spaces()),
-        attempt(comma().skip(not_name)),
+        optional_spaces().with(member).skip(optional_spaces()),
+        attempt(comma().skip(not_name).skip(not_followed_by(
+            optional_spaces().with(token('!')).map(|_| ""),
+        ))),
     )
     .map(RangeOutput::Disjoint)
 }
 
-fn range<I>() -> impl Parser<I, Output = Vec<RangeOutput>>
+/// A member of a bracket group: values, with the separator of their range if they are one, or a
+/// single value excluded from the others with `!`, and its leading zeros.
+enum Member {
+    Values(Option<RangeSep>, RangeOutput),
+    Excluded(usize, u64),
+}
+
+/// Fails on punctuation used in place of `,` between bracket members, as in `[1;2]`, so the error
+/// names the separator instead of only listing what was expected.
+fn stray_separator<I>(syntax: Syntax) -> impl Parser<I, Output = ()>
+where
+    I: Stream<Token = char>,
+    I::Error: ParseError<I::Token, I::Range, I::Position>,
+{
+    satisfy(move |c: char| {
+        c.is_ascii_punctuation()
+            && !matches!(c, ',' | '.' | ']')
+            && c != syntax.range_sep
+            && c != syntax.step_sep
+    })
+    .and_then(move |c| {
+        Err::<(), _>(StreamErrorFor::<I>::message_format(format!(
+            "unexpected separator {c:?}, expected ',' or {:?}",
+            syntax.range_sep
+        )))
+    })
+}
+
+/// Literal alternatives in a bracket group, as in `[web,db]`. Each starts with a letter that is not
+/// a digit of the radix, so `[ab]` is still a number in hexadecimal.
+fn disjoint_words<I>(syntax: Syntax) -> impl Parser<I, Output = RangeOutput>
+where
+    I: Stream<Token = char>,
+    I::Error: ParseError<I::Token, I::Range, I::Position>,
+{
+    let radix = syntax.numbers.radix;
+    let word_start = move || satisfy(move |c: char| c.is_alphabetic() && !c.is_digit(radix));
+    let word = word_start()
+        .and(many(alpha_num().or(dash()).or(token('.'))))
+        .map(|(c, rest): (char, String)| format!("{c}{rest}"));
+
+    sep_by1(
+        optional_spaces().with(word).skip(optional_spaces()),
+        attempt(comma().skip(look_ahead(optional_spaces().with(word_start())))),
+    )
+    .map(RangeOutput::Words)
+}
+
+fn range<I>(syntax: Syntax) -> impl Parser<I, Output = Vec<RangeOutput>>
 where
     I: Stream<Token = char>,
     I::Error: ParseError<I::Token, I::Range, I::Position>,
 {
+    let member = range_digits_sep(syntax)
+        .map(|(sep, range)| Member::Values(Some(sep), range))
+        .or(attempt(disjoint_words(syntax)).map(|range| Member::Values(None, range)))
+        .or(optional_spaces()
+            .with(token('!'))
+            .with(leading_zeros(syntax.numbers))
+            .skip(optional_spaces())
+            .map(|(zeros, value)| Member::Excluded(zeros, value)))
+        .or(disjoint_digits(syntax).map(|range| Member::Values(None, range)));
+
     between(
         open_bracket(),
-        close_bracket(),
-        sep_by1(range_digits().or(disjoint_digits()), comma()),
+        optional(stray_separator(syntax)).with(close_bracket()),
+        sep_by1(member, comma()).and_then(move |xs: Vec<Member>| {
+            let len = xs.len();
+            let (mut ranges, mut seps, mut excluded) = (vec![], vec![], vec![]);
+
+            for x in xs {
+                match x {
+                    Member::Values(sep, range) => {
+                        seps.extend(sep);
+                        ranges.push(range);
+                    }
+                    Member::Excluded(zeros, value) => excluded.push((zeros, value)),
+                }
+            }
+
+            let dash = seps.contains(&RangeSep::Dash);
+            let dots = seps
+                .iter()
+                .any(|sep| matches!(sep, RangeSep::Inclusive | RangeSep::Exclusive));
+            let words = ranges
+                .iter()
+                .any(|range| matches!(range, RangeOutput::Words(_)));
+
+            if dash && dots {
+                return Err(StreamErrorFor::<I>::unexpected_format(format!(
+                    "mixed `{}` and `..` range separators",
+                    syntax.range_sep
+                )));
+            } else if words && len > 1 {
+                return Err(StreamErrorFor::<I>::unexpected_static_message(
+                    "mixed words and numbers",
+                ));
+            } else if ranges.is_empty() {
+                return Err(StreamErrorFor::<I>::unexpected_static_message(
+                    "only excluded values",
+                ));
+            }
+
+            for (zeros, value) in excluded {
+                ranges = ranges
+                    .iter()
+                    .flat_map(|x| x.without(value, zeros, syntax.numbers))
+                    .collect();
+            }
+
+            if ranges.is_empty() {
+                Err(StreamErrorFor::<I>::unexpected_static_message(
+                    "every value excluded",
+                ))
+            } else {
+                Ok(ranges)
+            }
+        }),
     )
 }
 
-fn hostlist<I>() -> impl Parser<I, Output = Vec<Part>>
+/// Matches the first character of a [`Part`], without consuming it.
+fn part_start<I>() -> impl Parser<I, Output = char>
+where
+    I: Stream<Token = char>,
+    I::Error: ParseError<I::Token, I::Range, I::Position>,
+{
+    look_ahead(satisfy(|c: char| {
+        c.is_alphanumeric() || c == '-' || c == '.' || c == '"' || c == '['
+    }))
+}
+
+/// Fails on whitespace inside an entry in a strict syntax, naming it instead of only listing what
+/// was expected.
+fn stray_whitespace<I>() -> impl Parser<I, Output = String>
+where
+    I: Stream<Token = char>,
+    I::Error: ParseError<I::Token, I::Range, I::Position>,
+{
+    satisfy(char::is_whitespace).and_then(|_| {
+        Err::<String, _>(StreamErrorFor::<I>::unexpected_static_message(
+            "whitespace inside a host",
+        ))
+    })
+}
+
+/// Parses the parts of a single hostlist until `end` matches. `end` is only peeked at.
+///
+/// Whitespace before a host element is dropped, unless the syntax is strict, where it is only
+/// allowed at the start of the hostlist.
+fn hostlist_until<I, E>(end: E, syntax: Syntax) -> impl Parser<I, Output = Vec<Part>>
+where
+    I: Stream<Token = char>,
+    I::Error: ParseError<I::Token, I::Range, I::Position>,
+    E: Parser<I>,
+{
+    let string = if syntax.strict {
+        quoted().or(host_elements()).or(stray_whitespace()).left()
+    } else {
+        optional_spaces().with(quoted().or(host_elements())).right()
+    };
+    let leading = if syntax.strict {
+        optional_spaces().left()
+    } else {
+        value(None).right()
+    };
+
+    leading
+        .with(repeat_until(
+            choice([
+                range(syntax).map(Part::Range).left(),
+                string.map(Part::String).right(),
+            ]),
+            end,
+        ))
+        .and_then(|xs: Vec<_>| {
+            if xs.is_empty() {
+                Err(StreamErrorFor::<I>::unexpected_static_message(
+                    "no host found",
+                ))
+            } else {
+                Ok(xs)
+            }
+        })
+}
+
+fn hostlist<I>(syntax: Syntax) -> impl Parser<I, Output = Vec<Part>>
 where
     I: Stream<Token = char>,
     I::Error: ParseError<I::Token, I::Range, I::Position>,
 {
-    repeat_until(
-        choice([
-            range().map(Part::Range).left(),
-            optional_spaces()
-                .with(host_elements())
-                .map(Part::String)
-                .right(),
-        ]),
+    hostlist_until(
         attempt(optional_spaces().skip(ignore(comma()).or(eof()))),
+        syntax,
     )
-    .and_then(|xs: Vec<_>| {
-        if xs.is_empty() {
-            Err(StreamErrorFor::<I>::unexpected_static_message(
-                "no host found",
-            ))
-        } else {
-            Ok(xs)
-        }
-    })
 }
 
-fn hostlists<I>() -> impl Parser<I, Output = Vec<Vec<Part>>>
+fn hostlists<I>(syntax: Syntax) -> impl Parser<I, Output = Vec<Vec<Part>>>
+where
+    I: Stream<Token = char>,
+    I::Error: ParseError<I::Token, I::Range, I::Position>,
+{
+    sep_by1(hostlist(syntax), optional_spaces().with(comma()))
+}
+
+/// A combine parser for hostlist expressions, for embedding in a larger grammar.
+///
+/// Unlike [`parse`], it does not require the expression to run to the end of the input: it stops
+/// before the first character that cannot continue the expression, and leaves a trailing comma
+/// (or whitespace) that is not followed by another host for the enclosing grammar.
+///
+/// ```
+/// use combine::{parser::char::string, token, EasyParser, Parser};
+/// use hostlist_parser::hostlist_parser;
+///
+/// let (hosts, rest) = (string("hosts="), hostlist_parser(), token(';'))
+///     .map(|(_, hosts, _)| hosts)
+///     .easy_parse("hosts=oss[1-2].local, mds1;rest")
+///     .unwrap();
+///
+/// assert_eq!(hosts.len(), 2);
+/// assert_eq!(rest, "rest");
+/// ```
+pub fn hostlist_parser<I>() -> impl Parser<I, Output = Vec<ast::Hostlist>>
 where
     I: Stream<Token = char>,
     I::Error: ParseError<I::Token, I::Range, I::Position>,
 {
-    sep_by1(hostlist(), optional_spaces().with(comma()))
+    let end = attempt(optional_spaces().skip(not_followed_by(part_start())));
+    let separator = attempt(
+        optional_spaces()
+            .with(comma())
+            .skip(optional_spaces().with(part_start())),
+    );
+
+    sep_by1(
+        hostlist_until(end, Syntax::DEFAULT).map(|parts| ast::Hostlist::from_parts(&parts)),
+        separator,
+    )
+}
+
+fn parse_hostlists(input: &str) -> Result<Vec<Vec<Part>>, ParseErrors<'_>> {
+    parse_hostlists_with(input, Syntax::DEFAULT)
+}
+
+/// Parses `input` with bracket groups written as `syntax`.
+fn parse_hostlists_with(input: &str, syntax: Syntax) -> Result<Vec<Vec<Part>>, ParseErrors<'_>> {
+    let (hosts, _) = hostlists(syntax)
+        .easy_parse(input)
+        .map_err(|err| err.map_position(|p| p.translate_position(input)))?;
+
+    Ok(hosts)
 }
 
-pub fn parse(input: &str) -> Result<Vec<String>, combine::stream::easy::Errors<char, &str, usize>> {
-    let (hosts, _) = hostlists()
+/// Parses `input` as a single bracket group, such as `[1-4,7]`.
+fn parse_range(input: &str) -> Result<Vec<RangeOutput>, ParseErrors<'_>> {
+    let (xs, _) = range(Syntax::DEFAULT)
+        .skip(eof())
         .easy_parse(input)
         .map_err(|err| err.map_position(|p| p.translate_position(input)))?;
 
+    Ok(xs)
+}
+
+/// Returns the number of comma separated hostlists in `input` (`node[1-2],db1` has two), without
+/// expanding any hosts. Repeated hostlists are each counted.
+pub fn segment_count(input: &str) -> Result<usize, Error> {
+    Ok(parse_hostlists(input)?.len())
+}
+
+/// Returns the `(min, max)` numeric value of each bracket group in `input`, in order of appearance,
+/// without expanding any hosts. Groups of words, as in `[web,db]`, have no numeric value and are
+/// left out.
+pub fn range_bounds(input: &str) -> Result<Vec<(u64, u64)>, Error> {
+    let hosts = parse_hostlists(input)?;
+
+    Ok(hosts
+        .iter()
+        .flatten()
+        .filter_map(Part::get_ranges)
+        .filter(|xs| !matches!(xs[..], [RangeOutput::Words(_)]))
+        .map(|xs| bounds(xs))
+        .collect())
+}
+
+fn expand(hosts: Vec<Vec<Part>>) -> Vec<String> {
+    let hostlists: Vec<_> = hosts
+        .iter()
+        .map(|parts| ast::Hostlist::from_parts(parts))
+        .collect();
+
+    ast::expand(&hostlists)
+}
+
+/// Expands `input` into its hosts, in the order they are written, keeping the first of each
+/// repeated host.
+///
+/// The order only depends on `input`. Hash sets are used to spot repeats but never to order hosts,
+/// so the output is the same on every run, as it is for the other expanding functions.
+pub fn parse(input: &str) -> Result<Vec<String>, ParseErrors<'_>> {
+    let hosts = parse_hostlists(input)?;
+
+    Ok(expand(hosts))
+}
+
+/// Like [`parse`], but expands `input` lazily, generating each host as it is asked for. See
+/// [`HostlistIter`] for its size hint.
+///
+/// ```
+/// use hostlist_parser::parse_iter;
+///
+/// let hosts = parse_iter("node[1-1000000],node1").unwrap();
+///
+/// assert_eq!(hosts.size_hint(), (0, Some(1_000_001)));
+/// assert_eq!(hosts.take(2).collect::<Vec<_>>(), vec!["node1", "node2"]);
+///
+/// let hosts = parse_iter("node[1-3],node1").unwrap().keep_duplicates();
+///
+/// assert_eq!(hosts.size_hint(), (4, Some(4)));
+/// ```
+pub fn parse_iter(input: &str) -> Result<HostlistIter, Error> {
+    Ok(HostlistIter::new(Expansion::new(parse_hostlists(input)?)))
+}
+
+/// Like [`parse`], but first drops the noise that comes with an expression pasted from a shell
+/// command line: surrounding whitespace, a trailing `;`, and then a single pair of matching `"` or
+/// `'` quotes around the whole input.
+///
+/// Quotes inside the expression are kept, and still quote a literal host as in [`parse`].
+///
+/// ```
+/// use hostlist_parser::parse_shellish;
+///
+/// assert_eq!(parse_shellish("\"node[1-2]\";").unwrap(), vec!["node1", "node2"]);
+/// assert_eq!(parse_shellish("'node1'").unwrap(), vec!["node1"]);
+/// ```
+pub fn parse_shellish(input: &str) -> Result<Vec<String>, Error> {
+    let input = input.trim();
+    let input = input.strip_suffix(';').unwrap_or(input).trim_end();
+    let input = ['"', '\'']
+        .iter()
+        .find_map(|&q| input.strip_prefix(q)?.strip_suffix(q))
+        .unwrap_or(input);
+
+    Ok(parse(input)?)
+}
+
+/// Like [`parse`], for an expression received as bytes. Invalid UTF-8 fails with
+/// [`Error::InvalidUtf8`] and the offset of the first invalid byte; see
+/// [`ParseOptions::lossy_utf8`] to replace it instead. Parse errors are positioned by byte offset
+/// into `input` as well.
+///
+/// ```
+/// use hostlist_parser::{parse_bytes, Error};
+///
+/// assert_eq!(parse_bytes(b"oss[1-2]").unwrap(), vec!["oss1", "oss2"]);
+/// assert_eq!(parse_bytes(b"oss\xc3"), Err(Error::InvalidUtf8 { offset: 3 }));
+/// ```
+pub fn parse_bytes(input: &[u8]) -> Result<Vec<String>, Error> {
+    ParseOptions::new().parse_bytes(input)
+}
+
+/// Like [`parse`], but appends the hosts to `out`, so a caller parsing many expressions can reuse
+/// one buffer and its capacity. Clear `out` first to replace its contents.
+///
+/// Hosts repeated within `input` are only appended once, but they are not checked against what
+/// `out` already holds. Nothing is appended if `input` fails to parse.
+///
+/// ```
+/// use hostlist_parser::parse_into;
+///
+/// let mut hosts = vec!["mds1".to_string()];
+///
+/// parse_into("oss[1-2],oss1,mds1", &mut hosts).unwrap();
+///
+/// assert_eq!(hosts, vec!["mds1", "oss1", "oss2", "mds1"]);
+/// ```
+pub fn parse_into(input: &str, out: &mut Vec<String>) -> Result<(), Error> {
+    let hosts = parse_hostlists(input)?;
+    let mut seen = HashSet::new();
+
+    out.extend(Expansion::new(hosts).filter(|x| seen.insert(x.clone())));
+
+    Ok(())
+}
+
+/// Like [`parse`], pairing each host with its index in the full expansion, before repeats are
+/// removed. Indices follow the cartesian product order, with the last bracket group of an entry
+/// varying fastest.
+///
+/// A host that appears more than once keeps the index of its first appearance, so indices skip
+/// over the repeats that were dropped.
+///
+/// ```
+/// use hostlist_parser::parse_indexed;
+///
+/// assert_eq!(
+///     parse_indexed("n[1-2],n1,m1").unwrap(),
+///     vec![("n1".into(), 0), ("n2".into(), 1), ("m1".into(), 3)]
+/// );
+/// ```
+pub fn parse_indexed(input: &str) -> Result<Vec<(String, usize)>, Error> {
+    let hosts = parse_hostlists(input)?;
+    let mut seen = HashSet::new();
+
+    Ok(Expansion::new(hosts)
+        .enumerate()
+        .filter(|(_, x)| seen.insert(x.clone()))
+        .map(|(i, x)| (x, i))
+        .collect())
+}
+
+/// Like [`parse`], but takes hosts from each comma separated entry in turn, round-robin, rather
+/// than expanding the entries one after the other: `a[1-2],b[1-2]` gives `a1,b1,a2,b2`. This
+/// spreads work across racks listed as separate entries.
+///
+/// Entries of unequal length drop out once they run out, and the rest carry on in turn, so the
+/// longest entry's tail comes last. Repeats are dropped as with [`parse`], keeping the first host
+/// produced.
+///
+/// ```
+/// use hostlist_parser::parse_interleaved;
+///
+/// assert_eq!(
+///     parse_interleaved("a[1-3],b[1-2],c1").unwrap(),
+///     vec!["a1", "b1", "c1", "a2", "b2", "a3"]
+/// );
+/// ```
+pub fn parse_interleaved(input: &str) -> Result<Vec<String>, Error> {
+    let mut entries: Vec<_> = parse_hostlists(input)?
+        .into_iter()
+        .map(|parts| Expansion::new(vec![parts]))
+        .collect();
+    let mut seen = HashSet::new();
     let mut xs = vec![];
 
+    while !entries.is_empty() {
+        entries.retain_mut(|entry| match entry.next() {
+            Some(host) => {
+                if seen.insert(host.clone()) {
+                    xs.push(host);
+                }
+
+                true
+            }
+            None => false,
+        });
+    }
+
+    Ok(xs)
+}
+
+/// Parses each of `inputs`, and writes the union of their hosts as a single folded expression.
+///
+/// Entries with different literal text (`web*`, `db*`) are folded separately. The output is the
+/// [`Hostlist`] `Display` form, so it is deterministic and parses back to the same set.
+///
+/// ```
+/// use hostlist_parser::merge_compress;
+///
+/// assert_eq!(
+///     merge_compress(&["web[1-3],db1", "web[4-6]", "db2,web10"]).unwrap(),
+///     "db[1-2],web[1-6,10]"
+/// );
+/// ```
+pub fn merge_compress(inputs: &[&str]) -> Result<String, Error> {
+    let mut hostlist = Hostlist::new();
+
+    for input in inputs {
+        hostlist = hostlist.union(&Hostlist::parse(input)?);
+    }
+
+    Ok(hostlist.to_string())
+}
+
+/// Whether `a` and `b` expand to the same set of hosts, whatever their order, repeats or how their
+/// ranges are split. Ranges are compared merged, without expanding them.
+///
+/// Padding is part of a host name, so `node[01-03]` and `node[1-3]` differ. Both expressions are
+/// read with [`Hostlist::parse`], so hosts whose number is not in their last bracket group are
+/// expanded, up to [`Hostlist::EXPANSION_LIMIT`].
+///
+/// ```
+/// use hostlist_parser::equals;
+///
+/// assert_eq!(equals("node[1-3,5]", "node[5,1-2],node3"), Ok(true));
+/// assert_eq!(equals("node[01-03]", "node[1-3]"), Ok(false));
+/// ```
+pub fn equals(a: &str, b: &str) -> Result<bool, Error> {
+    Ok(Hostlist::parse(a)? == Hostlist::parse(b)?)
+}
+
+/// Whether `a` and `b` share any host, as when checking a new reservation against the active ones.
+///
+/// Ranges are compared without expanding them, stopping at the first shared host, so large ranges
+/// are as cheap as small ones. Padding is part of a host name, so `node01` and `node1` differ. As
+/// with [`equals`], hosts whose number is not in their last bracket group are expanded, up to
+/// [`Hostlist::EXPANSION_LIMIT`].
+///
+/// ```
+/// use hostlist_parser::overlaps;
+///
+/// assert_eq!(overlaps("node[1-1000000]", "node[999999-2000000]"), Ok(true));
+/// assert_eq!(overlaps("node[01-09]", "node[1-9]"), Ok(false));
+/// ```
+pub fn overlaps(a: &str, b: &str) -> Result<bool, Error> {
+    Ok(!Hostlist::parse(a)?.is_disjoint(&Hostlist::parse(b)?))
+}
+
+/// Parses `expr` and summarizes the numbers of its hosts as merged ranges per literal skeleton,
+/// without expanding them. See [`Hostlist::ranges`] for how hosts are split.
+///
+/// ```
+/// use hostlist_parser::ranges;
+///
+/// let xs = ranges("oss[1-48,53],oss[40-50],oss[60-64]").unwrap();
+///
+/// assert_eq!(xs.len(), 1);
+/// assert_eq!(xs[0].prefix, "oss");
+/// assert_eq!(xs[0].ranges, vec![1..=50, 53..=53, 60..=64]);
+/// ```
+pub fn ranges(expr: &str) -> Result<Vec<PrefixRanges>, Error> {
+    Ok(Hostlist::parse(expr)?.ranges())
+}
+
+/// Summarizes `input` in a short sentence, for a confirmation prompt before acting on a list of
+/// hosts: how many distinct hosts there are, the first and last in [`Hostlist`] iteration order,
+/// and how many contiguous runs they fall into when there are several.
+///
+/// Hosts are counted from the ranges, without expanding them. A list with no run longer than one
+/// host is described as specific hosts.
+///
+/// ```
+/// use hostlist_parser::describe;
+///
+/// assert_eq!(describe("node[001-048]").unwrap(), "48 hosts: node001 through node048");
+/// assert_eq!(
+///     describe("node[1-10,20-29]").unwrap(),
+///     "20 hosts in 2 ranges: node1 through node29"
+/// );
+/// assert_eq!(describe("node[1,3,5],mds1").unwrap(), "4 specific hosts: mds1 through node5");
+/// ```
+pub fn describe(input: &str) -> Result<String, Error> {
+    let hostlist = Hostlist::parse(input)?;
+    let (count, runs) = (hostlist.len(), hostlist.run_count());
+    let (first, last) = (
+        hostlist.first().unwrap_or_default(),
+        hostlist.last().unwrap_or_default(),
+    );
+
+    Ok(match count {
+        1 => format!("1 host: {first}"),
+        _ if runs == 1 => format!("{count} hosts: {first} through {last}"),
+        _ if runs as u128 == count => format!("{count} specific hosts: {first} through {last}"),
+        _ => format!("{count} hosts in {runs} ranges: {first} through {last}"),
+    })
+}
+
+/// Rewrites `expr` in a canonical folded form, so that expressions holding the same set of hosts
+/// normalize to the same string.
+///
+/// Hosts are merged and deduplicated, and written as the [`Hostlist`] `Display` form: grouped by
+/// the text around their last number, with ascending ranges. Padding and suffixes are kept as
+/// they are, and normalizing the output again gives it back unchanged.
+///
+/// ```
+/// use hostlist_parser::normalize;
+///
+/// assert_eq!(
+///     normalize("node[5,1-2],node3,mds1").unwrap(),
+///     normalize("mds1,node[1-3,5]").unwrap()
+/// );
+/// assert_eq!(normalize("node[3-1],node[02,01]").unwrap(), "node[01-02,1-3]");
+/// ```
+pub fn normalize(expr: &str) -> Result<String, Error> {
+    Ok(Hostlist::parse(expr)?.to_string())
+}
+
+/// Rewrites `expr` with entries that share a literal skeleton merged, so `h[1-3],h[4-6],h[10]`
+/// becomes `h[1-6,10]`.
+///
+/// Hosts are folded as by [`normalize`], but entries keep the order in which their skeleton first
+/// appears in `expr` instead of being sorted.
+///
+/// ```
+/// use hostlist_parser::compact;
+///
+/// assert_eq!(compact("h[1-3],mds1,h[4-6],h[10]").unwrap(), "h[1-6,10],mds1");
+/// assert_eq!(compact("web[3-4],db1,web[1-2]").unwrap(), "web[1-4],db1");
+/// ```
+pub fn compact(expr: &str) -> Result<String, Error> {
+    Hostlist::compact(expr)
+}
+
+/// Splits `expr` into `n` folded expressions whose sizes differ by at most one, and whose union is
+/// the whole set of hosts, to shard a nodeset across workers.
+///
+/// Hosts are dealt out in [`Hostlist`] iteration order, so numerically adjacent hosts stay in the
+/// same group and each group folds compactly. Ranges are split without expanding them. When `n`
+/// is larger than the number of hosts, the last groups are empty strings, so there are always `n`.
+///
+/// ```
+/// use hostlist_parser::split_n;
+///
+/// assert_eq!(
+///     split_n("node[1-10]", 3).unwrap(),
+///     vec!["node[1-4]", "node[5-7]", "node[8-10]"]
+/// );
+/// assert_eq!(split_n("mds1,mds2", 3).unwrap(), vec!["mds1", "mds2", ""]);
+/// ```
+///
+/// # Panics
+///
+/// Panics if `n` is 0.
+pub fn split_n(expr: &str, n: usize) -> Result<Vec<String>, Error> {
+    assert!(n > 0, "n must be non-zero");
+
+    let mut hostlist = Hostlist::parse(expr)?;
+    let len = hostlist.len();
+    let (size, extra) = (len / n as u128, len % n as u128);
+
+    Ok((0..n as u128)
+        .map(|i| {
+            hostlist
+                .take_first(size + u128::from(i < extra))
+                .to_string()
+        })
+        .collect())
+}
+
+/// Splits `input` into up to `n` folded expressions of consecutive hosts, in the order [`parse`]
+/// gives them, whose sizes differ by at most one.
+///
+/// Unlike [`split_n`], each worker gets a run of the hosts as written rather than as sorted, and
+/// when there are fewer hosts than `n` there are only as many expressions as hosts.
+///
+/// ```
+/// use hostlist_parser::split;
+///
+/// assert_eq!(
+///     split("gpu[1-3],cpu[1-4]", 3).unwrap(),
+///     vec!["gpu[1-3]", "cpu[1-2]", "cpu[3-4]"]
+/// );
+/// assert_eq!(split("mds1,mds2", 3).unwrap(), vec!["mds1", "mds2"]);
+/// ```
+///
+/// # Panics
+///
+/// Panics if `n` is 0.
+pub fn split(input: &str, n: usize) -> Result<Vec<String>, Error> {
+    assert!(n > 0, "n must be non-zero");
+
+    let hosts = parse(input)?;
+    let (size, extra) = (hosts.len() / n, hosts.len() % n);
+    let mut rest = &hosts[..];
+
+    Ok((0..n.min(hosts.len()))
+        .map(|i| {
+            let (chunk, tail) = rest.split_at(size + usize::from(i < extra));
+
+            rest = tail;
+
+            fold(chunk)
+        })
+        .collect())
+}
+
+/// Folds a list of host names into a compact expression, the inverse of [`parse`].
+///
+/// Each name is split around its last run of digits, and names sharing the text around it and the
+/// same zero padding are folded into one bracket group, with contiguous numbers written as ranges.
+/// Names without digits are kept as they are. See [`Hostlist`]'s `Display` for the exact output.
+///
+/// ```
+/// use hostlist_parser::fold;
+///
+/// assert_eq!(
+///     fold(["node01", "node02", "node03", "node04", "node07", "gw1"]),
+///     "gw1,node[01-04,07]"
+/// );
+/// ```
+pub fn fold<I: IntoIterator<Item = impl AsRef<str>>>(hosts: I) -> String {
+    hosts.into_iter().collect::<Hostlist>().to_string()
+}
+
+/// Like [`parse`], but never fails: an input that is not a valid hostlist is returned as a single
+/// host, as is.
+///
+/// ```
+/// use hostlist_parser::parse_or_literal;
+///
+/// assert_eq!(parse_or_literal("oss[1-2]"), vec!["oss1", "oss2"]);
+/// assert_eq!(parse_or_literal("weird_host!"), vec!["weird_host!"]);
+/// ```
+pub fn parse_or_literal(input: &str) -> Vec<String> {
+    parse(input).unwrap_or_else(|_| vec![input.to_string()])
+}
+
+/// Like [`parse`], but refuses to expand an expression that would generate more than `max_hosts`
+/// hosts. The count is computed from the ranges before anything is expanded, and includes hosts
+/// that would later be removed as duplicates.
+pub fn parse_with_limit(input: &str, max_hosts: usize) -> Result<Vec<String>, Error> {
+    let hosts = parse_hostlists(input)?;
+
+    let count = hosts
+        .iter()
+        .fold(0u128, |acc, parts| acc.saturating_add(cardinality(parts)));
+
+    if count > max_hosts as u128 {
+        return Err(Error::TooManyHosts {
+            count,
+            limit: max_hosts,
+        });
+    }
+
+    Ok(expand(hosts))
+}
+
+/// Expands `input` like [`parse`], grouping hosts by the literal text that starts each comma
+/// separated entry (everything before its first bracket group). Entries sharing a prefix are merged
+/// into one group, and groups are returned in order of first appearance. An entry starting with a
+/// bracket group is keyed by the empty string.
+///
+/// ```
+/// use hostlist_parser::parse_grouped;
+///
+/// let groups = parse_grouped("web[1-2],db[1-2]").unwrap();
+///
+/// assert_eq!(groups[0], ("web".to_string(), vec!["web1".to_string(), "web2".to_string()]));
+/// assert_eq!(groups[1].0, "db");
+/// ```
+pub fn parse_grouped(input: &str) -> Result<Vec<(String, Vec<String>)>, Error> {
+    let hosts = parse_hostlists(input)?;
+
+    let mut seen = HashSet::new();
+    let mut groups: Vec<(String, Vec<String>)> = vec![];
+
     for parts in hosts {
-        let x_prod: Vec<_> = parts
+        let prefix: String = parts
             .iter()
-            .filter_map(Part::get_ranges)
-            .map(|xs| flatten_ranges(xs))
-            .multi_cartesian_product()
+            .map_while(|p| match p {
+                Part::String(x) => Some(x.as_str()),
+                Part::Range(_) => None,
+            })
             .collect();
 
-        // No ranges means no interpolation
-        if x_prod.is_empty() {
-            let mut s = String::new();
-
-            for p in parts.clone() {
-                if let Part::String(x) = p {
-                    s.push_str(&x)
-                }
+        let idx = match groups.iter().position(|(x, _)| x == &prefix) {
+            Some(idx) => idx,
+            None => {
+                groups.push((prefix, vec![]));
+                groups.len() - 1
             }
+        };
 
-            xs.push(s);
-        } else {
-            for ys in x_prod {
-                let mut it = ys.iter();
-
-                let mut s = String::new();
+        groups[idx]
+            .1
+            .extend(Expansion::new(vec![parts]).filter(|x| seen.insert(x.clone())));
+    }
 
-                for p in parts.clone() {
-                    match p {
-                        Part::String(x) => s.push_str(&x),
-                        Part::Range(_) => s.push_str(it.next().unwrap()),
-                    }
-                }
+    Ok(groups)
+}
 
-                xs.push(s);
+/// Expands `expr` like [`parse`], grouping hosts by their skeleton: the host with each run of
+/// digits replaced by `*`, so `oss1.local` is in `oss*.local` and `r1n2` in `r*n*`. Hosts without
+/// digits are their own skeleton, and so each form a group of one.
+///
+/// Groups are returned in order of first appearance, each holding its hosts in expansion order.
+///
+/// ```
+/// use hostlist_parser::group_by_prefix;
+///
+/// let groups = group_by_prefix("oss[1-2].local,mds[1-2].local,gw").unwrap();
+///
+/// assert_eq!(groups[0].0, "oss*.local");
+/// assert_eq!(groups[0].1, vec!["oss1.local", "oss2.local"]);
+/// assert_eq!(groups[1].0, "mds*.local");
+/// assert_eq!(groups[2], ("gw".to_string(), vec!["gw".to_string()]));
+/// ```
+pub fn group_by_prefix(expr: &str) -> Result<Vec<(String, Vec<String>)>, Error> {
+    let mut index = HashMap::new();
+    let mut groups: Vec<(String, Vec<String>)> = vec![];
+
+    for host in parse(expr)? {
+        let mut skeleton = String::with_capacity(host.len());
+        let mut digits = false;
+
+        for c in host.chars() {
+            if !c.is_ascii_digit() {
+                skeleton.push(c);
+            } else if !digits {
+                skeleton.push('*');
             }
+
+            digits = c.is_ascii_digit();
         }
+
+        let idx = *index.entry(skeleton.clone()).or_insert_with(|| {
+            groups.push((skeleton, vec![]));
+            groups.len() - 1
+        });
+
+        groups[idx].1.push(host);
     }
 
-    Ok(xs.into_iter().unique().collect())
+    Ok(groups)
+}
+
+/// Returns the first `n` hosts [`parse`] would return for `input`, without expanding the rest.
+///
+/// Deduplication still applies, so hosts are generated until `n` distinct ones have been seen: at
+/// most `n` plus the number of duplicates that appear before the `n`th distinct host.
+pub fn parse_first_n(input: &str, n: usize) -> Result<Vec<String>, Error> {
+    let hosts = parse_hostlists(input)?;
+
+    Ok(Expansion::new(hosts).unique().take(n).collect())
+}
+
+/// Returns the first `n` hosts of the expansion of `input`, in the same order as [`parse`], along
+/// with the total number of hosts the expression generates. Hosts past the first `n` are counted
+/// from the ranges rather than expanded, so the total includes duplicates that [`parse`] would
+/// remove.
+pub fn parse_take(input: &str, n: usize) -> Result<(Vec<String>, u128), Error> {
+    let expansion = Expansion::new(parse_hostlists(input)?);
+    let total = expansion.remaining();
+
+    Ok((expansion.unique().take(n).collect(), total))
+}
+
+/// Expands `input` lazily in batches of `chunk_size` hosts, in the same order as [`parse`]. Only
+/// one batch is held at a time, and the last one may be smaller.
+///
+/// Hosts are not deduplicated in this mode, neither within nor across batches, so an expression
+/// that repeats a host yields it again.
+///
+/// # Panics
+///
+/// Panics if `chunk_size` is 0.
+///
+/// ```
+/// use hostlist_parser::expand_chunks;
+///
+/// let chunks: Vec<_> = expand_chunks("node[1-5]", 2).unwrap().collect();
+///
+/// assert_eq!(chunks, vec![vec!["node1", "node2"], vec!["node3", "node4"], vec!["node5"]]);
+/// ```
+pub fn expand_chunks(
+    input: &str,
+    chunk_size: usize,
+) -> Result<impl Iterator<Item = Vec<String>>, Error> {
+    assert!(chunk_size > 0, "chunk_size must be non-zero");
+
+    let mut hosts = Expansion::new(parse_hostlists(input)?);
+
+    Ok(std::iter::from_fn(move || {
+        let xs: Vec<_> = hosts.by_ref().take(chunk_size).collect();
+
+        (!xs.is_empty()).then_some(xs)
+    }))
+}
+
+/// Splits the expansion of `expr` into consecutive batches of at most `size` hosts, in the same
+/// order as [`parse`], each written as a folded expression. The last batch may be smaller.
+///
+/// Unlike [`split_n`], batches follow the order hosts appear in `expr`, as for a rolling restart.
+///
+/// ```
+/// use hostlist_parser::chunks;
+///
+/// assert_eq!(
+///     chunks("compute[001-025]", 10).unwrap(),
+///     vec!["compute[001-010]", "compute[011-020]", "compute[021-025]"]
+/// );
+/// ```
+///
+/// # Panics
+///
+/// Panics if `size` is 0.
+pub fn chunks(expr: &str, size: usize) -> Result<Vec<String>, Error> {
+    assert!(size > 0, "size must be non-zero");
+
+    let mut hosts = Expansion::new(parse_hostlists(expr)?).unique();
+
+    Ok(std::iter::from_fn(|| {
+        let xs: Hostlist = hosts.by_ref().take(size).collect();
+
+        (!xs.is_empty()).then(|| xs.to_string())
+    })
+    .collect())
 }
 
 #[cfg(test)]
@@ -255,16 +1223,23 @@ mod tests {
     use super::*;
     use combine::parser::EasyParser;
     use insta::assert_debug_snapshot;
+    use proptest::prelude::*;
 
     #[test]
     fn test_leading_zeros() {
-        assert_debug_snapshot!(leading_zeros().easy_parse("001"));
-        assert_debug_snapshot!(leading_zeros().easy_parse("0001"));
-        assert_debug_snapshot!(leading_zeros().easy_parse("01"));
-        assert_debug_snapshot!(leading_zeros().easy_parse("00"));
-        assert_debug_snapshot!(leading_zeros().easy_parse("0"));
-        assert_debug_snapshot!(leading_zeros().easy_parse("042"));
-        assert_debug_snapshot!(leading_zeros().easy_parse("042"));
+        assert_debug_snapshot!(leading_zeros(Numbers::DECIMAL).easy_parse("001"));
+        assert_debug_snapshot!(leading_zeros(Numbers::DECIMAL).easy_parse("0001"));
+        assert_debug_snapshot!(leading_zeros(Numbers::DECIMAL).easy_parse("01"));
+        assert_debug_snapshot!(leading_zeros(Numbers::DECIMAL).easy_parse("00"));
+        assert_debug_snapshot!(leading_zeros(Numbers::DECIMAL).easy_parse("0"));
+        assert_debug_snapshot!(leading_zeros(Numbers::DECIMAL).easy_parse("042"));
+        assert_debug_snapshot!(leading_zeros(Numbers::DECIMAL).easy_parse("042"));
+    }
+
+    #[test]
+    fn test_quoted() {
+        assert_debug_snapshot!(quoted().easy_parse(r#""weird[name],with,commas""#));
+        assert_debug_snapshot!(quoted().easy_parse(r#""escaped\"quote\\""#));
     }
 
     #[test]
@@ -277,36 +1252,37 @@ mod tests {
 
     #[test]
     fn test_disjoint_digits() {
-        assert_debug_snapshot!(disjoint_digits().easy_parse("1,2,3,4,5]"));
-        assert_debug_snapshot!(disjoint_digits().easy_parse("1,2,3-5"));
-        assert_debug_snapshot!(disjoint_digits().easy_parse("1,2,006,0007,3-5"));
+        assert_debug_snapshot!(disjoint_digits(Syntax::DEFAULT).easy_parse("1,2,3,4,5]"));
+        assert_debug_snapshot!(disjoint_digits(Syntax::DEFAULT).easy_parse("1,2,3-5"));
+        assert_debug_snapshot!(disjoint_digits(Syntax::DEFAULT).easy_parse("1,2,006,0007,3-5"));
+        assert_debug_snapshot!(disjoint_digits(Syntax::DEFAULT).easy_parse("1a,02b,3]"));
     }
 
     #[test]
     fn test_range() {
-        assert_debug_snapshot!(range().easy_parse("[1,2,3,4,5]"));
-        assert_debug_snapshot!(range().easy_parse("[1,2,3-5]"));
-        assert_debug_snapshot!(range().easy_parse("[1,2,3-5,6,7,8-10]"));
-        assert_debug_snapshot!(range().easy_parse("[01-10]"));
+        assert_debug_snapshot!(range(Syntax::DEFAULT).easy_parse("[1,2,3,4,5]"));
+        assert_debug_snapshot!(range(Syntax::DEFAULT).easy_parse("[1,2,3-5]"));
+        assert_debug_snapshot!(range(Syntax::DEFAULT).easy_parse("[1,2,3-5,6,7,8-10]"));
+        assert_debug_snapshot!(range(Syntax::DEFAULT).easy_parse("[01-10]"));
     }
 
     #[test]
     fn test_hostlist() {
-        assert_debug_snapshot!(hostlist().easy_parse("oss1.local"));
-        assert_debug_snapshot!(hostlist().easy_parse("oss[1,2].local"));
-        assert_debug_snapshot!(hostlist().easy_parse(
+        assert_debug_snapshot!(hostlist(Syntax::DEFAULT).easy_parse("oss1.local"));
+        assert_debug_snapshot!(hostlist(Syntax::DEFAULT).easy_parse("oss[1,2].local"));
+        assert_debug_snapshot!(hostlist(Syntax::DEFAULT).easy_parse(
             "hostname[2,6,7].iml.com,hostname[10,11-12,2-3,5].iml.com,hostname[15-17].iml.com"
         ));
     }
 
     #[test]
     fn test_hostlists() {
-        assert_debug_snapshot!(hostlists().easy_parse("oss1.local"));
-        assert_debug_snapshot!(hostlists().easy_parse("oss[1,2].local"));
-        assert_debug_snapshot!(hostlists().easy_parse(
+        assert_debug_snapshot!(hostlists(Syntax::DEFAULT).easy_parse("oss1.local"));
+        assert_debug_snapshot!(hostlists(Syntax::DEFAULT).easy_parse("oss[1,2].local"));
+        assert_debug_snapshot!(hostlists(Syntax::DEFAULT).easy_parse(
             "hostname[2,6,7].iml.com,hostname[10,11-12,2-3,5].iml.com,hostname[15-17].iml.com"
         ));
-        assert_debug_snapshot!(hostlists().easy_parse(
+        assert_debug_snapshot!(hostlists(Syntax::DEFAULT).easy_parse(
             "hostname[2,6,7].iml.com, hostname[10,11-12,2-3,5].iml.com, hostname[15-17].iml.com"
         ));
     }
@@ -487,9 +1463,965 @@ mod tests {
         assert_debug_snapshot!("Large expression", xs);
     }
 
+    #[test]
+    fn test_parse_quoted() {
+        assert_debug_snapshot!(
+            "Quoted host is emitted verbatim",
+            parse(r#""weird[name],with,commas""#)
+        );
+
+        assert_debug_snapshot!(
+            "Quoted hosts mixed with ranges",
+            parse(r#"node[1-2],"weird[name]", "x,y"[1-2]"#)
+        );
+
+        assert_debug_snapshot!("Quoted host with escaped quote", parse(r#""a\"b\\c""#));
+
+        assert_debug_snapshot!("Quoted host missing closing quote", parse(r#""node[1-2]"#));
+    }
+
+    #[test]
+    fn test_parse_single_char_between_ranges() {
+        assert_debug_snapshot!("Single letter between ranges", parse("a[1-2]x[3-4]"));
+        assert_debug_snapshot!("Single dot between ranges", parse("a[1-2].[3-4]"));
+        assert_debug_snapshot!("Single dash between ranges", parse("[1-2]-[3-4]b"));
+    }
+
+    #[test]
+    fn test_segment_count() {
+        assert_eq!(segment_count("node[1-2],db1"), Ok(2));
+        assert_eq!(segment_count("a, b[1-9999999], \"c,d\", a"), Ok(4));
+        assert_eq!(segment_count("mds1"), Ok(1));
+        assert_eq!(
+            segment_count("").map_err(|e| e.to_string()),
+            parse("").map(|_| 0).map_err(|e| e.to_string())
+        );
+    }
+
+    #[test]
+    fn test_range_bounds() {
+        assert_eq!(range_bounds("node[5,1,9-12]"), Ok(vec![(1, 12)]));
+        assert_eq!(range_bounds("node[7-3]"), Ok(vec![(3, 7)]));
+        assert_eq!(
+            range_bounds("oss[01-04]-ib[1,0].local,mds1"),
+            Ok(vec![(1, 4), (0, 1)])
+        );
+        assert_eq!(range_bounds("mds1"), Ok(vec![]));
+        assert!(range_bounds("node[1").is_err());
+    }
+
+    #[test]
+    fn test_merge_compress() {
+        assert_eq!(
+            merge_compress(&["web[1-3],db[01-02]", "db[03-04],web[2-5]", "gw"]),
+            Ok("db[01-04],web[1-5],gw".into())
+        );
+
+        let inputs = ["n[1-5],n[08-12]", "n[6-7],m1", "n[09-10],m[2-3]"];
+        let merged = merge_compress(&inputs).unwrap();
+
+        let mut expected: Vec<_> = inputs.iter().flat_map(|x| parse(x).unwrap()).collect();
+        expected.sort();
+        expected.dedup();
+
+        let mut actual = parse(&merged).unwrap();
+        actual.sort();
+
+        assert_eq!(actual, expected, "{merged}");
+        assert!(merge_compress(&["n[1-2]", "n[3"]).is_err());
+        assert_eq!(merge_compress(&[]), Ok(String::new()));
+    }
+
+    #[test]
+    fn test_equals() {
+        assert_eq!(equals("node[1-3,5]", "node[5,1-2],node3"), Ok(true));
+        assert_eq!(equals("node[01-03]", "node[1-3]"), Ok(false));
+        assert_eq!(equals("node[1-3]", "node[1-4]"), Ok(false));
+        assert_eq!(
+            equals("mds,node[0-99999999]", "node[0-99999999],mds,mds"),
+            Ok(true)
+        );
+        assert_eq!(equals("r[1-2]n[1-2]", "r1n[1-2],r2n[2,1]"), Ok(true));
+        assert_eq!(equals("node[3-1]", "node[1-3:1]"), Ok(true));
+        assert_eq!(equals("node[1-3]", "node[1-3]a"), Ok(false));
+        assert!(matches!(
+            equals("node[1-3]", "node[1-"),
+            Err(Error::Parse(_))
+        ));
+        assert!(matches!(
+            equals("n[0-99999999]-ib0", "n1-ib0"),
+            Err(Error::TooManyHosts { .. })
+        ));
+    }
+
+    #[test]
+    fn test_describe() {
+        assert_eq!(describe("node1").unwrap(), "1 host: node1");
+        assert_eq!(describe("node1,node1").unwrap(), "1 host: node1");
+        assert_eq!(
+            describe("node[1-3],node[2-5]").unwrap(),
+            "5 hosts: node1 through node5"
+        );
+        assert_eq!(
+            describe("oss[1-4].local,mds[1-2]").unwrap(),
+            "6 hosts in 2 ranges: mds1 through oss4.local"
+        );
+        assert_eq!(
+            describe("mds,oss").unwrap(),
+            "2 specific hosts: mds through oss"
+        );
+        assert_eq!(
+            describe("n[0-18446744073709551615]").unwrap(),
+            "18446744073709551616 hosts: n0 through n18446744073709551615"
+        );
+        assert!(matches!(describe("node[1-"), Err(Error::Parse(_))));
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            normalize("node[5,1-2],node3").unwrap(),
+            normalize("node[1-3,5]").unwrap()
+        );
+        assert_eq!(
+            normalize("slot[01a,2,3-4,5bc].local,oss[2,1].local").unwrap(),
+            "oss[1-2].local,slot[2-4].local,slot01a.local,slot5bc.local"
+        );
+        assert_ne!(
+            normalize("node[01-03]").unwrap(),
+            normalize("node[1-3]").unwrap()
+        );
+        assert!(matches!(normalize("node[1-"), Err(Error::Parse(_))));
+    }
+
+    #[test]
+    fn test_parse_interleaved() {
+        assert_eq!(
+            parse_interleaved("a[1-2],b[1-2]").unwrap(),
+            vec!["a1", "b1", "a2", "b2"]
+        );
+        assert_eq!(
+            parse_interleaved("r1n[1-4],r2n[1-2],r1n2,r3n1").unwrap(),
+            vec!["r1n1", "r2n1", "r1n2", "r3n1", "r2n2", "r1n3", "r1n4"]
+        );
+
+        let mut xs = parse_interleaved("oss[1-3],mds[1-2],mgs").unwrap();
+        let mut ys = parse("oss[1-3],mds[1-2],mgs").unwrap();
+
+        xs.sort();
+        ys.sort();
+
+        assert_eq!(xs, ys);
+        assert!(parse_interleaved("a[1-").is_err());
+    }
+
+    #[test]
+    fn test_overlaps() {
+        assert_eq!(overlaps("node[1-4],mds1", "oss1,mds1"), Ok(true));
+        assert_eq!(overlaps("r[1-2]n[1-2]", "r2n[2-3]"), Ok(true));
+        assert_eq!(overlaps("node[1-4]", "node[5-8],node01"), Ok(false));
+        assert_eq!(
+            overlaps("node[1-18446744073709551615]", "node18446744073709551615"),
+            Ok(true)
+        );
+        assert!(matches!(overlaps("node1", "node[1-"), Err(Error::Parse(_))));
+        assert!(matches!(
+            overlaps("r[1-99999999]n1", "r1n1"),
+            Err(Error::TooManyHosts { .. })
+        ));
+    }
+
+    #[test]
+    fn test_compact() {
+        assert_eq!(compact("h[1-3],h[4-6],h[10]").unwrap(), "h[1-6,10]");
+        assert_eq!(
+            compact("zeta[1-2],alpha1,gw,zeta3,alpha[2-3].ib,alpha2").unwrap(),
+            "zeta[1-3],alpha[1-2],gw,alpha[2-3].ib"
+        );
+        assert_eq!(
+            compact("n[3,1],n[01-02],n2").unwrap(),
+            normalize("n[3,1],n[01-02],n2").unwrap()
+        );
+
+        for input in [
+            "r[1-2]n[1-2],mds1,r1n3",
+            "oss[1-4].local,\"a,b\",oss5.local",
+        ] {
+            assert!(equals(input, &compact(input).unwrap()).unwrap());
+        }
+
+        assert!(matches!(compact("h[1-"), Err(Error::Parse(_))));
+    }
+
+    #[test]
+    fn test_parse_bytes() {
+        // Positions are byte offsets, so `é` counts twice.
+        match parse_bytes("\"é\",n[1-".as_bytes()) {
+            Err(Error::Parse(err)) => assert_eq!(err.position, 8),
+            x => panic!("{x:?}"),
+        }
+
+        assert_eq!(
+            parse_bytes(b"\"\xc3\xa9\",n\xc3"),
+            Err(Error::InvalidUtf8 { offset: 6 })
+        );
+    }
+
+    #[test]
+    fn test_deterministic_order() {
+        // Every `HashSet` and `HashMap` gets new random keys, so repeats would catch an order that
+        // follows them.
+        let input = "z[3-1],a[1-2],m1,z2,b[01-03],[web,db][1-2],a1,\"q r\",m[1-3]x,k";
+        let run = || {
+            (
+                parse(input).unwrap(),
+                parse_iter(input).unwrap().collect::<Vec<_>>(),
+                ParseOptions::new()
+                    .dedup(Dedup::KeepLast)
+                    .parse(input)
+                    .unwrap(),
+                ParseOptions::new()
+                    .numeric_dedup(true)
+                    .parse(input)
+                    .unwrap(),
+                group_by_prefix(input).unwrap(),
+                parse_interleaved(input).unwrap(),
+                parse_filtered(input, "*[12]").unwrap(),
+                compact(input).unwrap(),
+            )
+        };
+        let first = run();
+
+        for _ in 0..100 {
+            assert_eq!(run(), first);
+        }
+
+        assert_eq!(
+            first.0,
+            vec![
+                "z3", "z2", "z1", "a1", "a2", "m1", "b01", "b02", "b03", "web1", "web2", "db1",
+                "db2", "q r", "m1x", "m2x", "m3x", "k"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_iter() {
+        let input = "oss[1-3],oss2,mds[1-2]";
+        let mut hosts = parse_iter(input).unwrap();
+
+        assert_eq!(hosts.size_hint(), (0, Some(6)));
+        assert_eq!(hosts.next().as_deref(), Some("oss1"));
+        assert_eq!(hosts.size_hint(), (0, Some(5)));
+        assert_eq!(hosts.collect::<Vec<_>>(), parse(input).unwrap()[1..]);
+
+        let mut all = parse_iter(input).unwrap().keep_duplicates();
+
+        for left in (0..=6).rev() {
+            assert_eq!(all.size_hint(), (left, Some(left)));
+            all.next();
+        }
+
+        let xs: Vec<_> = parse_iter(input).unwrap().keep_duplicates().collect();
+
+        assert_eq!(xs.capacity(), 6);
+        assert!(matches!(parse_iter("oss[1-"), Err(Error::Parse(_))));
+    }
+
+    #[test]
+    fn test_parse_iter_rev() {
+        for input in [
+            "oss[1-3],oss2,mds[1-2]",
+            "n[5-1,9],m[1-2][b,a]",
+            "n[1,11][1,11],n111,x",
+            "n[1-3,2,01],\"q 1\",n[3-2]",
+        ] {
+            let mut expected = parse(input).unwrap();
+
+            expected.reverse();
+            assert_eq!(
+                parse_iter(input).unwrap().rev().collect::<Vec<_>>(),
+                expected
+            );
+        }
+
+        // Hosts whose number is not their last run of digits are not expanded either.
+        let mut hosts = parse_iter("n[0-99999999]-ib0,n[2-1]-ib0").unwrap();
+
+        assert_eq!(hosts.next_back().as_deref(), Some("n99999999-ib0"));
+        assert_eq!(hosts.next_back().as_deref(), Some("n99999998-ib0"));
+        assert_eq!(hosts.next().as_deref(), Some("n0-ib0"));
+        assert_eq!(
+            parse_iter("r[1-99999999]n1")
+                .unwrap()
+                .next_back()
+                .as_deref(),
+            Some("r99999999n1")
+        );
+
+        let mut hosts = parse_iter("n[1-4],n[2-6]").unwrap();
+
+        assert_eq!(hosts.next_back().as_deref(), Some("n6"));
+        assert_eq!(hosts.next().as_deref(), Some("n1"));
+        assert_eq!(hosts.next_back().as_deref(), Some("n5"));
+        assert_eq!(hosts.next_back().as_deref(), Some("n4"));
+        assert_eq!(hosts.next().as_deref(), Some("n2"));
+        assert_eq!(hosts.next().as_deref(), Some("n3"));
+        assert_eq!((hosts.next(), hosts.next_back()), (None, None));
+
+        assert_eq!(
+            parse_iter("n[1-18446744073709551615]")
+                .unwrap()
+                .rev()
+                .take(2)
+                .collect::<Vec<_>>(),
+            vec!["n18446744073709551615", "n18446744073709551614"]
+        );
+        assert_eq!(
+            parse_iter("n[1-2],n1")
+                .unwrap()
+                .keep_duplicates()
+                .rev()
+                .collect::<Vec<_>>(),
+            vec!["n1", "n2", "n1"]
+        );
+        assert_eq!(
+            parse_iter("gpu[1-4],cpu[1-9]")
+                .unwrap()
+                .rfind(|x| x.starts_with("gpu"))
+                .as_deref(),
+            Some("gpu4")
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn prop_parse_iter_both_ends(
+            xs in crate::ast::arbitrary::hostlists(),
+            ends in prop::collection::vec(prop::bool::ANY, 0..64),
+        ) {
+            let input = xs.iter().join(",");
+            let mut hosts = parse_iter(&input).unwrap();
+            let (mut front, mut back) = (vec![], vec![]);
+
+            for from_back in ends {
+                if from_back {
+                    back.extend(hosts.next_back());
+                } else {
+                    front.extend(hosts.next());
+                }
+            }
+
+            front.extend(hosts.by_ref());
+            front.extend(back.into_iter().rev());
+
+            prop_assert_eq!(front, parse(&input).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_parse_iter_nth() {
+        // Nothing is generated for the skipped hosts, so these are instant.
+        let mut hosts = parse_iter("n[1-18446744073709551615],m[1-3]").unwrap();
+
+        assert_eq!(
+            hosts.nth(1_000_000_000_000).as_deref(),
+            Some("n1000000000001")
+        );
+        assert_eq!(
+            hosts
+                .nth(18_446_744_073_709_551_613 - 1_000_000_000_001)
+                .as_deref(),
+            Some("n18446744073709551614")
+        );
+        assert_eq!(hosts.skip(1).collect::<Vec<_>>(), vec!["m1", "m2", "m3"]);
+        assert_eq!(
+            parse_iter("r[1-1000]n[1-1000000]")
+                .unwrap()
+                .skip(500_000_000)
+                .take(2)
+                .collect::<Vec<_>>(),
+            vec!["r501n1", "r501n2"]
+        );
+
+        // An entry whose number is not its last run of digits is not folded to skip a few hosts.
+        let mut hosts = parse_iter("n[0-99999999]-ib0").unwrap();
+
+        assert_eq!(hosts.nth(5).as_deref(), Some("n5-ib0"));
+        assert_eq!(hosts.nth(50_000_000).as_deref(), Some("n50000006-ib0"));
+        assert_eq!(
+            parse_iter("mds,r[1-99999999]n1,mds,r[4-6]n1")
+                .unwrap()
+                .nth(6)
+                .as_deref(),
+            Some("r6n1")
+        );
+
+        // Repeats are still dropped across skipped entries.
+        let input = "n[1-5],n[3-8],m[1,1,2],n1";
+
+        for n in 0..12 {
+            assert_eq!(
+                parse_iter(input).unwrap().skip(n).collect::<Vec<_>>(),
+                parse(input)
+                    .unwrap()
+                    .into_iter()
+                    .skip(n)
+                    .collect::<Vec<_>>()
+            );
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn prop_parse_iter_nth(
+            xs in crate::ast::arbitrary::hostlists(),
+            steps in prop::collection::vec((0..40usize, prop::bool::ANY), 0..16),
+            keep_duplicates in prop::bool::ANY,
+        ) {
+            let input = xs.iter().join(",");
+            let (mut hosts, mut model): (_, std::collections::VecDeque<_>) = if keep_duplicates {
+                (
+                    parse_iter(&input).unwrap().keep_duplicates(),
+                    Expansion::new(parse_hostlists(&input).unwrap()).collect(),
+                )
+            } else {
+                (parse_iter(&input).unwrap(), parse(&input).unwrap().into())
+            };
+
+            for (n, from_back) in steps {
+                if from_back {
+                    prop_assert_eq!(hosts.next_back(), model.pop_back());
+                } else {
+                    model.drain(..n.min(model.len()));
+                    prop_assert_eq!(hosts.nth(n), model.pop_front());
+                }
+            }
+
+            prop_assert_eq!(hosts.collect::<Vec<_>>(), Vec::from(model));
+        }
+    }
+
+    #[test]
+    fn test_parse_shellish() {
+        let expected = Ok(vec!["node1".to_string(), "node2".to_string()]);
+
+        assert_eq!(parse_shellish("\"node[1-2]\""), expected);
+        assert_eq!(parse_shellish("node[1-2];"), expected);
+        assert_eq!(parse_shellish(" 'node[1-2]' ; "), expected);
+        assert_eq!(parse_shellish("'node1'"), Ok(vec!["node1".to_string()]));
+        assert_eq!(
+            parse_shellish(r#"node1,"weird[name]""#),
+            Ok(vec!["node1".to_string(), "weird[name]".to_string()])
+        );
+        assert_eq!(
+            parse_shellish(r#"'"weird[name]"'"#),
+            Ok(vec!["weird[name]".to_string()])
+        );
+        assert!(parse_shellish("\"node1'").is_err());
+        assert!(parse_shellish("node1;;").is_err());
+    }
+
+    #[test]
+    fn test_parse_into() {
+        let mut out = Vec::with_capacity(16);
+
+        parse_into("node[1-3],node[2-4]", &mut out).unwrap();
+        assert_eq!(out, parse("node[1-3],node[2-4]").unwrap());
+
+        out.clear();
+        parse_into("mds1", &mut out).unwrap();
+        parse_into("mds[1-2]", &mut out).unwrap();
+        assert_eq!(out, vec!["mds1", "mds1", "mds2"]);
+        assert!(out.capacity() >= 16);
+
+        assert!(matches!(
+            parse_into("node[1-", &mut out),
+            Err(Error::Parse(_))
+        ));
+        assert_eq!(out.len(), 3);
+    }
+
+    #[test]
+    fn test_chunks() {
+        assert_eq!(chunks("compute[001-100]", 10).unwrap().len(), 10);
+        assert_eq!(
+            chunks("compute[001-100]", 10).unwrap()[1],
+            "compute[011-020]"
+        );
+        assert_eq!(
+            chunks("node[5-1],mds1,node[3-7]", 3).unwrap(),
+            vec!["node[3-5]", "mds1,node[1-2]", "node[6-7]"]
+        );
+        assert_eq!(chunks("node[1-2]", 5).unwrap(), vec!["node[1-2]"]);
+        assert!(matches!(chunks("node[1-", 2), Err(Error::Parse(_))));
+    }
+
+    #[test]
+    fn test_parse_indexed() {
+        assert_debug_snapshot!(parse_indexed("r[1-2]n[1-2],r1n2,r3n1"));
+
+        let input = "oss[1-3].local,oss[2-5].local";
+
+        assert_eq!(
+            parse_indexed(input)
+                .unwrap()
+                .into_iter()
+                .map(|(x, _)| x)
+                .collect::<Vec<_>>(),
+            parse(input).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_group_by_prefix() {
+        assert_debug_snapshot!(group_by_prefix(
+            "oss[1-2].local,mds[1-2].local,r[1-2]n1,0[1-2],gw,oss01.local,7"
+        ));
+        assert!(matches!(group_by_prefix("node[1-"), Err(Error::Parse(_))));
+    }
+
+    #[test]
+    fn test_split_n() {
+        assert_eq!(
+            split_n("oss[1-3].local,mds[1-2],node[01-05],gw", 4).unwrap(),
+            vec![
+                "mds[1-2],node01",
+                "node[02-04]",
+                "node05,oss[1-2].local",
+                "oss3.local,gw"
+            ]
+        );
+        assert_eq!(split_n("node[1-3]", 1).unwrap(), vec!["node[1-3]"]);
+        assert_eq!(
+            split_n("node[0-99999999999]", 2).unwrap(),
+            vec!["node[0-49999999999]", "node[50000000000-99999999999]"]
+        );
+        assert!(matches!(split_n("node[1-", 2), Err(Error::Parse(_))));
+    }
+
+    #[test]
+    #[should_panic(expected = "n must be non-zero")]
+    fn test_split_n_zero() {
+        let _ = split_n("node1", 0);
+    }
+
+    #[test]
+    fn test_split() {
+        let input = "oss[1-3].local,mds[1-2],node[01-05],gw";
+
+        assert_eq!(
+            split(input, 4).unwrap(),
+            vec![
+                "oss[1-3].local",
+                "mds[1-2],node01",
+                "node[02-04]",
+                "node05,gw"
+            ]
+        );
+        assert_eq!(
+            split(input, 4)
+                .unwrap()
+                .iter()
+                .flat_map(|x| parse(x).unwrap())
+                .collect::<HashSet<_>>(),
+            parse(input).unwrap().into_iter().collect()
+        );
+        assert_eq!(split("node[3-1]", 1).unwrap(), vec!["node[1-3]"]);
+        assert_eq!(split("n1,n1,n2", 5).unwrap(), vec!["n1", "n2"]);
+        assert!(matches!(split("node[1-", 2), Err(Error::Parse(_))));
+    }
+
+    #[test]
+    #[should_panic(expected = "n must be non-zero")]
+    fn test_split_zero() {
+        let _ = split("node1", 0);
+    }
+
+    #[test]
+    fn test_fold() {
+        assert_eq!(
+            fold(["node1", "node01", "node2", "node02"]),
+            "node[1-2,01-02]"
+        );
+        assert_eq!(
+            fold(["mds", "oss1.local", "oss2.local"]),
+            "oss[1-2].local,mds"
+        );
+        assert_eq!(fold(Vec::<String>::new()), "");
+        assert_eq!(fold(parse("node[1-3,5],node[4-9]").unwrap()), "node[1-9]");
+    }
+
+    proptest! {
+        #[test]
+        fn prop_fold_round_trip(
+            hosts in prop::collection::vec(
+                prop_oneof![
+                    "[a-z][a-z.-]{0,3}[0-9]{1,4}[a-z.-]{0,2}",
+                    "[a-z0-9][a-z0-9.-]{0,7}",
+                ],
+                1..20,
+            )
+        ) {
+            let expr = fold(&hosts);
+
+            let expected: HashSet<_> = hosts.into_iter().collect();
+            let actual: HashSet<_> = parse(&expr).unwrap().into_iter().collect();
+
+            prop_assert_eq!(actual, expected, "{}", expr);
+        }
+
+        #[test]
+        fn prop_normalize_idempotent(xs in ast::arbitrary::hostlists()) {
+            let expr = xs.iter().join(",");
+            let normalized = normalize(&expr).unwrap();
+
+            prop_assert_eq!(normalize(&normalized).unwrap(), normalized.clone());
+            prop_assert_eq!(equals(&expr, &normalized), Ok(true));
+        }
+
+        #[test]
+        fn prop_split_n(xs in ast::arbitrary::hostlists(), n in 1..8usize) {
+            let expr = xs.iter().join(",");
+            let groups = split_n(&expr, n).unwrap();
+
+            prop_assert_eq!(groups.len(), n);
+
+            let sizes: Vec<_> = groups
+                .iter()
+                .map(|x| Hostlist::parse(x).map_or(0, |x| x.len()))
+                .collect();
+
+            prop_assert!(sizes.iter().max().unwrap() - sizes.iter().min().unwrap() <= 1);
+            prop_assert_eq!(equals(&groups.iter().filter(|x| !x.is_empty()).join(","), &expr), Ok(true));
+        }
+
+        #[test]
+        fn prop_fold_expression_round_trip(xs in ast::arbitrary::hostlists()) {
+            let expr = xs.iter().join(",");
+            let hosts = parse(&expr).unwrap();
+
+            let expected: HashSet<_> = hosts.iter().cloned().collect();
+
+            for folded in [fold(&hosts), fold_nd(&hosts), Hostlist::parse(&expr).unwrap().to_string()] {
+                let actual: HashSet<_> = parse(&folded).unwrap().into_iter().collect();
+
+                prop_assert_eq!(&actual, &expected, "{} -> {}", expr, folded);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_or_literal() {
+        assert_eq!(
+            parse_or_literal("node[1-2],node[2-3]"),
+            vec!["node1", "node2", "node3"]
+        );
+        assert_eq!(parse_or_literal("node[1-2"), vec!["node[1-2"]);
+        assert_eq!(parse_or_literal("a_b.example.com"), vec!["a_b.example.com"]);
+        assert_eq!(parse_or_literal(""), vec![""]);
+    }
+
+    #[test]
+    fn test_parse_with_limit() {
+        assert_eq!(
+            parse_with_limit("node[1-3]", 3),
+            Ok(vec!["node1".into(), "node2".into(), "node3".into()])
+        );
+
+        assert_eq!(
+            parse_with_limit("node[0-999999999]", 10_000),
+            Err(Error::TooManyHosts {
+                count: 1_000_000_000,
+                limit: 10_000
+            })
+        );
+
+        assert_eq!(
+            parse_with_limit("node[1-2]-ib[0-1],mds[1,2]", 5),
+            Err(Error::TooManyHosts { count: 6, limit: 5 })
+        );
+
+        assert_debug_snapshot!("Parse with limit error", parse_with_limit("node[1", 10));
+    }
+
+    #[test]
+    fn test_parse_suffixed_members() {
+        assert_debug_snapshot!(
+            "Disjoint members with alpha suffixes",
+            parse("slot[1a,2a,3b]")
+        );
+
+        assert_debug_snapshot!(
+            "Disjoint members mixing suffixes, padding and ranges",
+            parse("slot[01a,2,3-4,5bc].local")
+        );
+
+        assert_debug_snapshot!("Suffix is not allowed on a range end", parse("slot[1-3a]"));
+    }
+
+    #[test]
+    fn test_parse_word_groups() {
+        assert_eq!(
+            parse("[web,db][1-2].com").unwrap(),
+            vec!["web1.com", "web2.com", "db1.com", "db2.com"]
+        );
+        assert_eq!(
+            parse("rack1-[ leaf, spine-a ]").unwrap(),
+            vec!["rack1-leaf", "rack1-spine-a"]
+        );
+        assert_eq!(
+            Hostlist::parse("n[1-2][a,b]").unwrap().to_string(),
+            "n[1-2]a,n[1-2]b"
+        );
+        // Hex digits are numbers, not words.
+        assert_eq!(
+            ParseOptions::new().radix(16).parse("n[a-c]"),
+            Ok(vec!["na".into(), "nb".into(), "nc".into()])
+        );
+        assert_eq!(parse("n[a-c]").unwrap(), vec!["na-c"]);
+        assert_eq!(range_bounds("[a,b]n[3-4]").unwrap(), vec![(3, 4)]);
+        assert_eq!(
+            ast::parse_ast("[web,db][01-02]").unwrap()[0].to_string(),
+            "[web,db][01-02]"
+        );
+
+        assert_debug_snapshot!("Words mixed with numbers", parse("n[a,1]"));
+    }
+
+    #[test]
+    fn test_parse_exclusions() {
+        assert_eq!(
+            parse("node[1-10,!5,!7]").unwrap(),
+            vec!["node1", "node2", "node3", "node4", "node6", "node8", "node9", "node10"]
+        );
+        assert_eq!(
+            parse("n[5-1,!3,8, !1]").unwrap(),
+            vec!["n5", "n4", "n2", "n8"]
+        );
+        assert_eq!(parse("n[1-10:3,!4,!7]").unwrap(), vec!["n1", "n10"]);
+        assert_eq!(parse("n[10-1:3,!7,!5]").unwrap(), vec!["n10", "n4", "n1"]);
+        assert_eq!(parse("n[1,2a,2,!2]").unwrap(), vec!["n1", "n2a"]);
+        // Padding must match for a value to be excluded.
+        assert_eq!(parse("n[01-03,!2,!03]").unwrap(), vec!["n01", "n02"]);
+        assert_eq!(parse("n[1-3,!9]").unwrap(), vec!["n1", "n2", "n3"]);
+        assert_eq!(
+            ast::parse_ast("n[1-10,!5]").unwrap()[0].to_string(),
+            "n[1-4,6-10]"
+        );
+
+        assert_debug_snapshot!("Only excluded values", parse("n[!1]"));
+        assert_debug_snapshot!("Every value excluded", parse("n[1-2,!1,!2]"));
+    }
+
+    #[test]
+    fn test_padding_boundaries() {
+        // Values keep the written width of the padded end, whatever their own number of digits.
+        assert_eq!(
+            parse("n[08-12]").unwrap(),
+            vec!["n08", "n09", "n10", "n11", "n12"]
+        );
+        assert_eq!(
+            parse("n[098-102]").unwrap(),
+            vec!["n098", "n099", "n100", "n101", "n102"]
+        );
+        assert_eq!(
+            parse("n[0998-1002]").unwrap(),
+            vec!["n0998", "n0999", "n1000", "n1001", "n1002"]
+        );
+        assert_eq!(
+            parse("n[102-098]").unwrap(),
+            vec!["n102", "n101", "n100", "n099", "n098"]
+        );
+        assert_eq!(
+            parse("n[0098-101]").unwrap()[..3],
+            ["n0098", "n0099", "n0100"]
+        );
+
+        // Without padding, values are written as they are.
+        assert_eq!(
+            parse("n[998-1002]").unwrap(),
+            vec!["n998", "n999", "n1000", "n1001", "n1002"]
+        );
+
+        // The same padding on both ends is kept on every value instead.
+        assert_eq!(
+            parse("n[08-011]").unwrap(),
+            vec!["n08", "n09", "n010", "n011"]
+        );
+    }
+
+    #[test]
+    fn test_padding_mismatch_position() {
+        let err = parse("hostname[9-0011]").unwrap_err();
+        assert_eq!(err.position, 11);
+
+        let err = parse("hostname[1,01 - 009]").unwrap_err();
+        assert_eq!(err.position, 16);
+
+        let err = parse("hostname[0011-9]").unwrap_err();
+        assert_eq!(err.position, 14);
+    }
+
+    #[test]
+    fn test_stray_separator() {
+        assert_debug_snapshot!(parse("node[1;2]"));
+        assert_debug_snapshot!(parse("node[1-3/5]"));
+        assert_debug_snapshot!(parse("node[1,2|3]"));
+
+        let err = parse("node[1-3;5]").unwrap_err();
+        assert_eq!(err.position, 8);
+        assert_eq!(
+            err.to_string(),
+            "Parse error at 8\nunexpected separator ';', expected ',' or '-'\n"
+        );
+    }
+
+    #[test]
+    fn test_hostlist_parser() {
+        let parse_embedded = |input: &'static str| {
+            hostlist_parser()
+                .easy_parse(input)
+                .map(|(xs, rest): (Vec<ast::Hostlist>, _)| {
+                    (xs.iter().map(|x| x.to_string()).collect::<Vec<_>>(), rest)
+                })
+                .map_err(|err| err.map_position(|p| p.translate_position(input)))
+        };
+
+        assert_debug_snapshot!(parse_embedded("oss[1-2].local, mds1;rest"));
+        assert_debug_snapshot!(parse_embedded("oss[1-2].local, ;rest"));
+        assert_debug_snapshot!(parse_embedded("oss[1-2].local )"));
+        assert_debug_snapshot!(parse_embedded("oss[1-2"));
+    }
+
+    #[test]
+    fn test_parse_dot_ranges() {
+        assert_debug_snapshot!("Exclusive range", parse("node[1..5]"));
+        assert_debug_snapshot!("Inclusive dot range", parse("node[1..=5]"));
+        assert_debug_snapshot!("Reversed exclusive range", parse("node[5..1]"));
+        assert_debug_snapshot!(
+            "Exclusive range with padding",
+            parse("node[008..011,2,012..=13]")
+        );
+        assert_debug_snapshot!("Empty exclusive range", parse("node[1..1]"));
+        assert_debug_snapshot!("Mixed range separators", parse("node[1..3,5-6]"));
+        assert_debug_snapshot!("Dot range padding mismatch", parse("node[1..=005]"));
+    }
+
+    #[test]
+    fn test_parse_take() {
+        assert_debug_snapshot!(parse_take("node[001-16384]", 3));
+        assert_debug_snapshot!(parse_take("hostname[1,2]-[3-4]-[5,6].iml.com,mds1", 5));
+        assert_debug_snapshot!(parse_take("node[1-2],node[2-3]", 10));
+
+        let (xs, total) = parse_take("node[0-999999999]-ib[0-3]", 1).unwrap();
+        assert_eq!(xs, vec!["node0-ib0".to_string()]);
+        assert_eq!(total, 4_000_000_000);
+    }
+
+    #[test]
+    fn test_expand_chunks() {
+        assert_debug_snapshot!(expand_chunks("node[1-2],node[2-3],mds", 2)
+            .unwrap()
+            .collect::<Vec<_>>());
+
+        let mut chunks = expand_chunks("node[0-999999999999]", 1000).unwrap();
+        assert_eq!(chunks.nth(2).unwrap()[0], "node2000");
+
+        assert_eq!(expand_chunks("mds", 10).unwrap().count(), 1);
+        assert!(expand_chunks("node[1", 10).is_err());
+    }
+
+    #[test]
+    fn test_parse_grouped() {
+        assert_debug_snapshot!(parse_grouped(
+            "web[1-2],db[1-2],web[2-3].local,[1-2]x,db3,\"w\"eb[9]"
+        ));
+        assert!(parse_grouped("").is_err());
+    }
+
+    #[test]
+    fn test_parse_first_n() {
+        assert_eq!(
+            parse_first_n("node[1-2],node[1-3],mds[1-1000000000000]", 4),
+            Ok(vec![
+                "node1".into(),
+                "node2".into(),
+                "node3".into(),
+                "mds1".into()
+            ])
+        );
+        assert_eq!(parse_first_n("node[1-2]", 0), Ok(vec![]));
+        assert_eq!(parse_first_n("node[1-2]", 5).unwrap().len(), 2);
+        assert!(parse_first_n("node[", 5).is_err());
+    }
+
+    #[test]
+    fn test_expansion_matches_parse() {
+        for input in [
+            "oss[1,2].local",
+            "hostname[10,11-12,002-003,5].iml.com",
+            "hostname[7-5], hostname[8,9], hostname[3,2,1]",
+            "hostname[1,2]-[3-4]-[5,6].iml.com",
+            "hostname[1,2][3,4].iml.com",
+            "hostname[1,2-3].iml[2,3].com,hostname[3,4,5].iml[3,4].com",
+            "hostname[06-10],test[000-002].localdomain",
+            "hostname4.iml.com,hostname4.iml.com",
+            "OST01[00,01]",
+            "n[098-102,08-011,011-08],n[08-012:3,0998-1002]",
+        ] {
+            let hosts = parse_hostlists(input).unwrap();
+
+            assert_eq!(
+                Expansion::new(hosts).unique().collect::<Vec<_>>(),
+                parse(input).unwrap(),
+                "{input}"
+            );
+        }
+    }
+
     #[test]
 
     fn test_parse_osts() {
         assert_debug_snapshot!("Leading 0s", parse("OST01[00,01]"));
     }
+
+    #[test]
+    fn test_parse_steps() {
+        assert_debug_snapshot!("Steps among disjoint values", parse("n[1,4-10:2,15]"));
+        assert_debug_snapshot!("Step with trailing value", parse("n[0-8:4,9]"));
+        assert_eq!(
+            parse("n[10-1:3,02-06 : 2]").unwrap(),
+            vec!["n10", "n7", "n4", "n1", "n02", "n04", "n06"]
+        );
+        assert_eq!(
+            parse("n[1..=9:4,0..6:5]").unwrap(),
+            vec!["n1", "n5", "n9", "n0"]
+        );
+        assert_eq!(parse("n[1-3:1]"), parse("n[1-3]"));
+        assert_eq!(range_bounds("n[1-10:4]"), Ok(vec![(1, 9)]));
+        assert_debug_snapshot!("Zero step", parse("n[1-5:0]"));
+    }
+
+    #[test]
+    fn test_parse_keeps_literal_order() {
+        assert_eq!(parse("a,b[1-2],c").unwrap(), vec!["a", "b1", "b2", "c"]);
+        assert_debug_snapshot!(
+            "Literals between ranges",
+            parse("x[3,1],lit1,y[2-1],lit2,z[1]")
+        );
+        assert_debug_snapshot!(
+            "Repeated literal keeps first position",
+            parse("c,a[1-2],b,c,a1,d")
+        );
+    }
+
+    #[test]
+    fn test_parse_digits_before_range() {
+        assert_eq!(parse("OST01[00,01]").unwrap(), vec!["OST0100", "OST0101"]);
+        assert_debug_snapshot!("Digit before a disjoint group", parse("OST9[8,9]"));
+        assert_debug_snapshot!("Zero before a range", parse("x0[0-2]"));
+        assert_debug_snapshot!("Digits on both sides", parse("r1[1-2]0"));
+        assert_debug_snapshot!("Digits between groups", parse("r[1-2]0[01-02]"));
+    }
 }
@@ -0,0 +1,195 @@
+// Copyright (c) 2022 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+use crate::{
+    ast::{self, Expr},
+    structures::num_digits,
+    Error,
+};
+use std::{collections::HashMap, fmt};
+
+/// A likely mistake in an expression that still parses. `segment` is the index of the comma
+/// separated entry it was found in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Lint {
+    /// Literal digits directly before a bracket group, as in `node12[3-4]`. The digits stay part of
+    /// the literal, so this expands to `node123,node124` rather than a range over `12x`.
+    DigitsBeforeRange { segment: usize, digits: String },
+    /// The same field zero-padded to different widths, as in `node[1-9],node[01-09]`. `prefix` is
+    /// the text before the field, with earlier bracket groups written `[]`, and `widths` are the
+    /// paddings seen in increasing order, `0` standing for none.
+    MixedPadding {
+        prefix: String,
+        segments: Vec<usize>,
+        widths: Vec<usize>,
+    },
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Lint::DigitsBeforeRange { segment, digits } => write!(
+                f,
+                "Entry {segment}: literal digits {digits:?} directly before a range are kept as text"
+            ),
+            Lint::MixedPadding {
+                prefix,
+                segments,
+                widths,
+            } => write!(
+                f,
+                "Entries {segments:?}: numbers after {prefix:?} are padded to different widths {widths:?}"
+            ),
+        }
+    }
+}
+
+/// Parses `input` and reports likely mistakes in it. An empty result means nothing was found; the
+/// expression is parsed the same way either way.
+///
+/// ```
+/// use hostlist_parser::{parse_lint, Lint};
+///
+/// let lints = parse_lint("node12[3-4],mds1").unwrap();
+///
+/// assert_eq!(lints.len(), 1);
+/// assert!(matches!(&lints[0], Lint::DigitsBeforeRange { segment: 0, digits } if digits == "12"));
+/// ```
+pub fn parse_lint(input: &str) -> Result<Vec<Lint>, Error> {
+    let hostlists = ast::parse_ast(input)?;
+
+    let mut lints: Vec<_> = hostlists
+        .iter()
+        .enumerate()
+        .flat_map(|(segment, hostlist)| {
+            hostlist.exprs.windows(2).filter_map(move |xs| match xs {
+                [Expr::Literal(x), Expr::Bracket(_)] => {
+                    let digits = &x[x.trim_end_matches(|c: char| c.is_ascii_digit()).len()..];
+
+                    (!digits.is_empty()).then(|| Lint::DigitsBeforeRange {
+                        segment,
+                        digits: digits.to_string(),
+                    })
+                }
+                _ => None,
+            })
+        })
+        .collect();
+
+    lints.extend(mixed_padding(&hostlists));
+
+    Ok(lints)
+}
+
+/// How a field is written in one range: the entry it is in, its padding if any zeros are added,
+/// and the number of digits of its smallest value.
+struct Written {
+    segment: usize,
+    padding: Option<usize>,
+    digits: usize,
+}
+
+/// Groups the ranges of every entry by the field they are in, and reports fields where some of
+/// the values get zeros a range elsewhere leaves out.
+fn mixed_padding(hostlists: &[ast::Hostlist]) -> Vec<Lint> {
+    let mut fields: Vec<(String, Vec<Written>)> = vec![];
+    let mut index = HashMap::new();
+
+    for (segment, hostlist) in hostlists.iter().enumerate() {
+        let mut prefix = String::new();
+
+        for expr in &hostlist.exprs {
+            let ranges = match expr {
+                Expr::Literal(x) => {
+                    prefix.push_str(x);
+
+                    continue;
+                }
+                Expr::Bracket(xs) => xs,
+                Expr::Words(_) => {
+                    prefix.push_str("[]");
+
+                    continue;
+                }
+            };
+
+            let i = *index.entry(prefix.clone()).or_insert_with(|| {
+                fields.push((prefix.clone(), vec![]));
+
+                fields.len() - 1
+            });
+
+            fields[i].1.extend(ranges.iter().map(|x| {
+                let digits = num_digits(x.start.min(x.end));
+
+                Written {
+                    segment,
+                    padding: (x.width > digits).then_some(x.width),
+                    digits,
+                }
+            }));
+
+            prefix.push_str("[]");
+        }
+    }
+
+    fields
+        .into_iter()
+        .filter_map(|(prefix, written)| {
+            let widest = written.iter().filter_map(|x| x.padding).max()?;
+            let clashing: Vec<_> = written
+                .iter()
+                .filter(|x| x.padding.is_some() || x.digits < widest)
+                .collect();
+
+            let mut widths: Vec<_> = clashing.iter().map(|x| x.padding.unwrap_or(0)).collect();
+            let mut segments: Vec<_> = clashing.iter().map(|x| x.segment).collect();
+
+            widths.sort_unstable();
+            widths.dedup();
+            segments.sort_unstable();
+            segments.dedup();
+
+            (widths.len() > 1).then_some(Lint::MixedPadding {
+                prefix,
+                segments,
+                widths,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use insta::assert_debug_snapshot;
+
+    #[test]
+    fn test_digits_before_range() {
+        assert_debug_snapshot!(parse_lint("node12[3-4]"));
+        assert_eq!(parse("node12[3-4]").unwrap(), vec!["node123", "node124"]);
+
+        assert_debug_snapshot!(parse_lint("a[1-2],r1[1-2]n0[1,2],OST01[00,01]"));
+        assert_eq!(parse_lint("node[1-2],node1,r[1-2]n[3-4]"), Ok(vec![]));
+        assert!(parse_lint("node1[").is_err());
+    }
+
+    #[test]
+    fn test_mixed_padding() {
+        assert_debug_snapshot!(parse_lint("node[1-9],mds1,node[01-09]"));
+        assert_debug_snapshot!(parse_lint("r[1-2]n[001-010],r[3-4]n[01-10],r5n[1-9]"));
+        assert_eq!(
+            parse_lint("node[01-09,10-99],node[100-200],r[1-2]n[1-4],rack[01-02]"),
+            Ok(vec![])
+        );
+        // Literal digits are not a field.
+        assert_eq!(parse_lint("node[01-09],node5"), Ok(vec![]));
+        assert_eq!(
+            parse_lint("node[01-09],node[5]").unwrap()[0].to_string(),
+            "Entries [0, 1]: numbers after \"node\" are padded to different widths [0, 2]"
+        );
+    }
+}
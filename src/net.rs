@@ -0,0 +1,85 @@
+// Copyright (c) 2022 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+use crate::{parse, Error};
+use std::{
+    io,
+    net::{SocketAddr, ToSocketAddrs},
+};
+
+/// A host of an expression that could not be resolved, see [`parse_socket_addrs`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct ResolveFailure {
+    pub host: String,
+    pub error: io::Error,
+}
+
+/// Expands `input` like [`crate::parse`], and resolves each host to its addresses at `port`, in
+/// order.
+///
+/// A host that does not resolve is reported as a [`ResolveFailure`] alongside the addresses of
+/// the others, rather than failing the whole expression. Resolution is blocking, one host at a
+/// time.
+///
+/// ```
+/// use hostlist_parser::parse_socket_addrs;
+/// use std::net::SocketAddr;
+///
+/// let (addrs, failures) = parse_socket_addrs("10.0.0.[1-2]", 988).unwrap();
+///
+/// assert_eq!(
+///     addrs,
+///     vec![
+///         "10.0.0.1:988".parse::<SocketAddr>().unwrap(),
+///         "10.0.0.2:988".parse().unwrap()
+///     ]
+/// );
+/// assert!(failures.is_empty());
+/// ```
+pub fn parse_socket_addrs(
+    input: &str,
+    port: u16,
+) -> Result<(Vec<SocketAddr>, Vec<ResolveFailure>), Error> {
+    let mut addrs = vec![];
+    let mut failures = vec![];
+
+    for host in parse(input)? {
+        match (host.as_str(), port).to_socket_addrs() {
+            Ok(xs) => addrs.extend(xs),
+            Err(error) => failures.push(ResolveFailure { host, error }),
+        }
+    }
+
+    Ok((addrs, failures))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_socket_addrs() {
+        // A name with a NUL byte fails before any lookup, so the test needs no resolver.
+        let (addrs, failures) =
+            parse_socket_addrs("192.168.1.[9-10],\"bad\0name\",\"::1\"", 22).unwrap();
+
+        assert_eq!(
+            addrs,
+            vec![
+                "192.168.1.9:22".parse::<SocketAddr>().unwrap(),
+                "192.168.1.10:22".parse().unwrap(),
+                "[::1]:22".parse().unwrap(),
+            ]
+        );
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].host, "bad\0name");
+        assert_eq!(failures[0].error.kind(), io::ErrorKind::InvalidInput);
+
+        assert!(matches!(
+            parse_socket_addrs("n[1-", 22),
+            Err(Error::Parse(_))
+        ));
+    }
+}
@@ -0,0 +1,943 @@
+// Copyright (c) 2022 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+use crate::{
+    ast, expand,
+    iter::Expansion,
+    parse_hostlists_with,
+    read::ReadHosts,
+    sort::cmp_hosts,
+    structures::{Numbers, Part, RangeOutput, Syntax},
+    Error, Hostlist,
+};
+use std::{borrow::Cow, collections::HashSet, io::BufRead};
+
+/// Which copy of a repeated host [`ParseOptions::parse`] keeps.
+///
+/// A host is repeated when the expression produces it more than once, whether from separate
+/// sub-expressions (`node1,node[1-2]`) or overlapping ranges in one bracket group (`node[1-3,2]`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dedup {
+    /// Keep a host where it first appears.
+    #[default]
+    KeepFirst,
+    /// Keep a host where it last appears.
+    KeepLast,
+    /// Keep every copy, so repeats can be counted.
+    None,
+}
+
+/// Options controlling how an expression is expanded.
+///
+/// ```
+/// use hostlist_parser::ParseOptions;
+///
+/// let hosts = ParseOptions::new().sort(true).parse("node[10,2,1]").unwrap();
+///
+/// assert_eq!(hosts, vec!["node1", "node2", "node10"]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    sort: bool,
+    dedup: Dedup,
+    error_on_duplicate: bool,
+    max_range_width: Option<usize>,
+    case_insensitive_dedup: bool,
+    max_input_len: Option<usize>,
+    radix: Option<u32>,
+    signed: bool,
+    numeric_dedup: bool,
+    lossy_utf8: bool,
+    reverse_digits: bool,
+    range_sep: Option<char>,
+    step_sep: Option<char>,
+    range_word: Option<&'static str>,
+    strict: bool,
+}
+
+impl ParseOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return hosts in natural order (`node2` before `node10`) instead of order of appearance.
+    pub fn sort(mut self, sort: bool) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    /// Choose which copy of a repeated host to keep, see [`Dedup`]. Repeats are found after
+    /// [`ParseOptions::case_insensitive_dedup`] and [`ParseOptions::numeric_dedup`] are applied.
+    pub fn dedup(mut self, dedup: Dedup) -> Self {
+        self.dedup = dedup;
+        self
+    }
+
+    /// Fail with [`Error::DuplicateHost`] if the expression produces the same host more than once,
+    /// instead of silently dropping the repeats.
+    pub fn error_on_duplicate(mut self, error_on_duplicate: bool) -> Self {
+        self.error_on_duplicate = error_on_duplicate;
+        self
+    }
+
+    /// Treat hosts that only differ in case (`Node1`, `node1`) as the same host, keeping the casing
+    /// seen first.
+    pub fn case_insensitive_dedup(mut self, case_insensitive_dedup: bool) -> Self {
+        self.case_insensitive_dedup = case_insensitive_dedup;
+        self
+    }
+
+    /// Treat hosts that only differ in the zero padding of their numbers (`node1`, `node01`) as the
+    /// same host, keeping the form seen first.
+    pub fn numeric_dedup(mut self, numeric_dedup: bool) -> Self {
+        self.numeric_dedup = numeric_dedup;
+        self
+    }
+
+    /// Fail with [`Error::RangeTooWide`] if any single bracket group holds more than
+    /// `max_range_width` values, whatever the total number of hosts.
+    pub fn max_range_width(mut self, max_range_width: usize) -> Self {
+        self.max_range_width = Some(max_range_width);
+        self
+    }
+
+    /// Fail with [`Error::InputTooLong`] if the expression is longer than `max_input_len` bytes,
+    /// before any parsing is done.
+    pub fn max_input_len(mut self, max_input_len: usize) -> Self {
+        self.max_input_len = Some(max_input_len);
+        self
+    }
+
+    /// Read and write the numbers in bracket groups in `radix`, which must be 8, 10 or 16. The
+    /// default is 10.
+    ///
+    /// Padding is kept in the chosen radix: `node[00-10]` in octal gives `node00` to `node10`. A
+    /// digit outside the radix is an error. Hex digits are read in either case and written in
+    /// lower case; as they are letters, `node[1a]` in hex is the single number `0x1a` rather than
+    /// `1` with an `a` suffix. Steps (`[0-20:4]`) are always decimal, and [`ParseOptions::sort`]
+    /// compares runs of decimal digits, so it does not order hex numbers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `radix` is not 8, 10 or 16.
+    pub fn radix(mut self, radix: u32) -> Self {
+        assert!(
+            matches!(radix, 8 | 10 | 16),
+            "radix must be 8, 10 or 16, got {radix}"
+        );
+
+        self.radix = Some(radix);
+        self
+    }
+
+    /// Read a `-` directly before a number in a bracket group as its sign, so `temp[-2-2]` is
+    /// `temp-2` to `temp2`. Numbers are then `i64`s rather than `u64`s.
+    ///
+    /// A `-` is a sign where a number is expected, and a range separator after one: `[-5--2]` is
+    /// `-5` to `-2`. Padding applies after the sign, as in `-02`. [`ParseOptions::sort`] compares
+    /// digits without their sign.
+    pub fn signed(mut self, signed: bool) -> Self {
+        self.signed = signed;
+        self
+    }
+
+    /// Write the digits of every number from a bracket group in reverse, least significant first,
+    /// for hardware that labels its devices that way: `node[10-12]` gives `node01`, `node11` and
+    /// `node21`.
+    ///
+    /// Only the output is affected: the expression is read as usual, and hosts keep their order,
+    /// though [`ParseOptions::sort`] orders the names as written.
+    /// Zero padding is added before the digits are reversed, so `[08-10]` gives `80`, `90` and
+    /// `01`. A sign stays in front.
+    pub fn reverse_digits(mut self, reverse_digits: bool) -> Self {
+        self.reverse_digits = reverse_digits;
+        self
+    }
+
+    /// Separate the ends of a range in a bracket group with `range_sep` instead of `-`, as in
+    /// `node[1/4]`. The `..` and `..=` forms are still read.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range_sep` cannot be used, see [`ParseOptions::try_range_sep`].
+    pub fn range_sep(self, range_sep: char) -> Self {
+        self.try_range_sep(range_sep)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Like [`ParseOptions::range_sep`], but fails with [`Error::InvalidSeparator`] rather than
+    /// panic if `range_sep` is the step separator, or is not a separator that can be used, see
+    /// [`ParseOptions::try_step_sep`].
+    pub fn try_range_sep(mut self, range_sep: char) -> Result<Self, Error> {
+        check_separator(range_sep)?;
+
+        if range_sep == self.syntax().step_sep {
+            return Err(Error::InvalidSeparator {
+                sep: range_sep,
+                reason: "it is already the step separator",
+            });
+        }
+
+        self.range_sep = Some(range_sep);
+        Ok(self)
+    }
+
+    /// Separate a range from its step with `step_sep` instead of `:`, as in `node[1-9/2]`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `step_sep` cannot be used, see [`ParseOptions::try_step_sep`].
+    pub fn step_sep(self, step_sep: char) -> Self {
+        self.try_step_sep(step_sep)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Like [`ParseOptions::step_sep`], but fails with [`Error::InvalidSeparator`] rather than
+    /// panic if `step_sep` is the range separator, or is not ASCII punctuation. `,`, `.`, `[`,
+    /// `]`, `"` and `\` are taken by the rest of the syntax, so they cannot be used either.
+    pub fn try_step_sep(mut self, step_sep: char) -> Result<Self, Error> {
+        check_separator(step_sep)?;
+
+        if step_sep == self.syntax().range_sep {
+            return Err(Error::InvalidSeparator {
+                sep: step_sep,
+                reason: "it is already the range separator",
+            });
+        }
+
+        self.step_sep = Some(step_sep);
+        Ok(self)
+    }
+
+    /// Also separate the ends of a range with `word`, as in `node[1 to 5]`, which is read like
+    /// `node[1-5]`. The word is only read between two numbers and only as a whole word, so
+    /// `[1,today]` is unaffected.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `word` is empty or has anything but ASCII letters.
+    pub fn range_word(mut self, word: &'static str) -> Self {
+        assert!(
+            !word.is_empty() && word.chars().all(|c| c.is_ascii_alphabetic()),
+            "{word:?} cannot be used as a range word"
+        );
+
+        self.range_word = Some(word);
+        self
+    }
+
+    /// Reject whitespace inside an entry, which is otherwise dropped, so `node1 garbage` fails
+    /// rather than giving `node1garbage`. Whitespace around the commas between entries is still
+    /// allowed. Leading, trailing and doubled commas are rejected either way.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Have [`ParseOptions::parse_bytes`] replace invalid UTF-8 with `U+FFFD` rather than fail.
+    /// A host holding a replacement character is not a valid name, so this only lets a parse go
+    /// through when the invalid bytes are inside a quoted host.
+    pub fn lossy_utf8(mut self, lossy_utf8: bool) -> Self {
+        self.lossy_utf8 = lossy_utf8;
+        self
+    }
+
+    /// Like [`ParseOptions::parse`], for an expression received as bytes.
+    ///
+    /// Invalid UTF-8 fails with [`Error::InvalidUtf8`] and the offset of the first invalid byte,
+    /// unless [`ParseOptions::lossy_utf8`] is set.
+    pub fn parse_bytes(&self, input: &[u8]) -> Result<Vec<String>, Error> {
+        let input = if self.lossy_utf8 {
+            String::from_utf8_lossy(input)
+        } else {
+            std::str::from_utf8(input)
+                .map(Cow::Borrowed)
+                .map_err(|err| Error::InvalidUtf8 {
+                    offset: err.valid_up_to(),
+                })?
+        };
+
+        self.parse(&input)
+    }
+
+    pub fn parse(&self, input: &str) -> Result<Vec<String>, Error> {
+        if let Some(limit) = self.max_input_len {
+            if input.len() > limit {
+                return Err(Error::InputTooLong {
+                    len: input.len(),
+                    limit,
+                });
+            }
+        }
+
+        let syntax = self.syntax();
+        let numbers = syntax.numbers;
+        let hosts = parse_hostlists_with(input, syntax)?;
+
+        if let Some(limit) = self.max_range_width {
+            check_range_width(&hosts, limit)?;
+        }
+
+        if numbers == Numbers::DECIMAL
+            && !self.sort
+            && self.dedup == Dedup::KeepFirst
+            && !self.error_on_duplicate
+            && !self.case_insensitive_dedup
+            && !self.numeric_dedup
+        {
+            return Ok(expand(hosts));
+        }
+
+        let mut seen = HashSet::new();
+        let mut xs = vec![];
+
+        for host in Expansion::new(hosts).with_numbers(numbers) {
+            let first = seen.insert(self.dedup_key(&host));
+
+            if !first && self.error_on_duplicate {
+                return Err(Error::DuplicateHost { host });
+            }
+
+            if first || self.dedup != Dedup::KeepFirst {
+                xs.push(host);
+            }
+        }
+
+        if self.dedup == Dedup::KeepLast {
+            seen.clear();
+            xs.reverse();
+            xs.retain(|x| seen.insert(self.dedup_key(x)));
+            xs.reverse();
+        }
+
+        if self.sort {
+            xs.sort_by(|a, b| cmp_hosts(a, b));
+        }
+
+        Ok(xs)
+    }
+
+    /// What a host is compared by to find repeats.
+    fn dedup_key(&self, host: &str) -> String {
+        let key = if self.case_insensitive_dedup {
+            host.to_lowercase()
+        } else {
+            host.to_string()
+        };
+
+        if self.numeric_dedup {
+            strip_padding(&key)
+        } else {
+            key
+        }
+    }
+
+    fn syntax(&self) -> Syntax {
+        Syntax {
+            numbers: Numbers {
+                radix: self.radix.unwrap_or(10),
+                signed: self.signed,
+                reverse_digits: self.reverse_digits,
+            },
+            range_sep: self.range_sep.unwrap_or(Syntax::DEFAULT.range_sep),
+            step_sep: self.step_sep.unwrap_or(Syntax::DEFAULT.step_sep),
+            range_word: self.range_word,
+            strict: self.strict,
+        }
+    }
+}
+
+fn check_separator(sep: char) -> Result<(), Error> {
+    let reason = if !sep.is_ascii_punctuation() {
+        "it is not ASCII punctuation"
+    } else if matches!(sep, ',' | '.' | '[' | ']' | '"' | '\\') {
+        "it is taken by the rest of the syntax"
+    } else {
+        return Ok(());
+    };
+
+    Err(Error::InvalidSeparator { sep, reason })
+}
+
+/// Options controlling how host names are folded into an expression, see [`crate::fold`].
+///
+/// ```
+/// use hostlist_parser::FoldOptions;
+///
+/// let hosts = ["node1", "node01", "node2", "node10"];
+///
+/// assert_eq!(FoldOptions::new().fold(hosts), "node[01,1-2,10]");
+/// assert_eq!(FoldOptions::new().merge_padding(true).fold(hosts), "node[01-02,10]");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FoldOptions {
+    merge_padding: bool,
+}
+
+impl FoldOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rewrite numbers sharing a prefix and suffix to the widest zero padding seen among them
+    /// before folding, so differently padded spellings of a number land in the same bracket.
+    ///
+    /// This is lossy: `node1` and `node01` both come out as `node01`, so the output no longer
+    /// parses back to the input. By default every padding is kept as is and folded separately.
+    pub fn merge_padding(mut self, merge_padding: bool) -> Self {
+        self.merge_padding = merge_padding;
+        self
+    }
+
+    pub fn fold<I: IntoIterator<Item = impl AsRef<str>>>(&self, hosts: I) -> String {
+        let mut hostlist: Hostlist = hosts.into_iter().collect();
+
+        if self.merge_padding {
+            hostlist.merge_padding();
+        }
+
+        hostlist.to_string()
+    }
+}
+
+/// Options controlling how [`ReadOptions::parse_read`] splits its input.
+///
+/// ```
+/// use hostlist_parser::ReadOptions;
+///
+/// let hosts: Vec<_> = ReadOptions::new()
+///     .comment(Some(';'))
+///     .parse_read("node[1-2] ; compute\n".as_bytes())
+///     .collect::<Result<_, _>>()
+///     .unwrap();
+///
+/// assert_eq!(hosts, vec!["node1", "node2"]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReadOptions {
+    comment: Option<char>,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        Self { comment: Some('#') }
+    }
+}
+
+impl ReadOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start comments with `comment` instead of `#`, or read every line in full with `None`. A
+    /// comment runs to the end of its line, unless the character is inside a quoted host.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `comment` is not ASCII punctuation, or is one of `,`, `.`, `[`, `]`, `"`, `\`,
+    /// `-`, `:`, `!` and `@`, which are taken by the rest of the syntax.
+    pub fn comment(mut self, comment: Option<char>) -> Self {
+        if let Some(c) = comment {
+            assert!(
+                check_separator(c).is_ok() && !matches!(c, '-' | ':' | '!' | '@'),
+                "{c:?} cannot be used as a comment character"
+            );
+        }
+
+        self.comment = comment;
+        self
+    }
+
+    /// Like [`crate::parse_read`], with these options.
+    pub fn parse_read(&self, r: impl BufRead) -> impl Iterator<Item = Result<String, Error>> {
+        ReadHosts::new(r, self.comment.map(|c| c as u8))
+    }
+}
+
+/// Drops the leading zeros of every number in `host`, keeping a single `0`.
+fn strip_padding(host: &str) -> String {
+    let mut xs = String::with_capacity(host.len());
+    let mut chars = host.chars().peekable();
+    let mut in_number = false;
+
+    while let Some(c) = chars.next() {
+        let leading_zero =
+            !in_number && c == '0' && chars.peek().is_some_and(|c| c.is_ascii_digit());
+
+        in_number = c.is_ascii_digit() && !leading_zero;
+
+        if !leading_zero {
+            xs.push(c);
+        }
+    }
+
+    xs
+}
+
+fn check_range_width(hosts: &[Vec<Part>], limit: usize) -> Result<(), Error> {
+    for parts in hosts {
+        for (i, part) in parts.iter().enumerate() {
+            let Part::Range(xs) = part else {
+                continue;
+            };
+
+            let width = xs.iter().map(RangeOutput::cardinality).sum::<u128>();
+
+            if width > limit as u128 {
+                let range = ast::Hostlist::from_parts(parts).exprs[i].to_string();
+
+                return Err(Error::RangeTooWide {
+                    range,
+                    width,
+                    limit,
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use insta::assert_debug_snapshot;
+
+    #[test]
+    fn test_sort() {
+        assert_debug_snapshot!(ParseOptions::new()
+            .sort(true)
+            .parse("node[10,2,1],node[01-02],mds1.b.com,mds1.a.com,node2"));
+
+        assert_eq!(
+            ParseOptions::new().parse("node[10,2,1]"),
+            Ok(vec!["node10".into(), "node2".into(), "node1".into()])
+        );
+    }
+
+    #[test]
+    fn test_error_on_duplicate() {
+        let options = ParseOptions::new().error_on_duplicate(true);
+
+        assert_debug_snapshot!(options.parse("hostname4.iml.com,hostname4.iml.com"));
+        assert_debug_snapshot!(options.parse("node[1-5],node[4-8]"));
+        assert_eq!(
+            options.sort(true).parse("node[10,2,1]"),
+            Ok(vec!["node1".into(), "node2".into(), "node10".into()])
+        );
+        assert_eq!(
+            ParseOptions::new().parse("hostname4.iml.com,hostname4.iml.com"),
+            Ok(vec!["hostname4.iml.com".into()])
+        );
+    }
+
+    #[test]
+    fn test_dedup() {
+        let input = "node[1-3,2-4],node1";
+        let parse = |dedup| ParseOptions::new().dedup(dedup).parse(input).unwrap();
+
+        assert_eq!(
+            parse(Dedup::KeepFirst),
+            vec!["node1", "node2", "node3", "node4"]
+        );
+        assert_eq!(
+            parse(Dedup::KeepLast),
+            vec!["node2", "node3", "node4", "node1"]
+        );
+        assert_eq!(
+            parse(Dedup::None),
+            vec!["node1", "node2", "node3", "node2", "node3", "node4", "node1"]
+        );
+        assert_eq!(
+            ParseOptions::new()
+                .dedup(Dedup::KeepLast)
+                .case_insensitive_dedup(true)
+                .parse("Node1,node[1-2]"),
+            Ok(vec!["node1".into(), "node2".into()])
+        );
+        assert_eq!(
+            ParseOptions::new()
+                .dedup(Dedup::None)
+                .sort(true)
+                .parse("node[2,1,2]"),
+            Ok(vec!["node1".into(), "node2".into(), "node2".into()])
+        );
+        assert!(ParseOptions::new()
+            .dedup(Dedup::None)
+            .error_on_duplicate(true)
+            .parse(input)
+            .is_err());
+    }
+
+    #[test]
+    fn test_case_insensitive_dedup() {
+        let options = ParseOptions::new().case_insensitive_dedup(true);
+
+        assert_debug_snapshot!(options.parse("Node[1-2],node[2-3]"));
+        assert_eq!(
+            options.clone().sort(true).parse("node[2-3],Node[1-2]"),
+            Ok(vec!["Node1".into(), "node2".into(), "node3".into()])
+        );
+        assert_debug_snapshot!(options
+            .error_on_duplicate(true)
+            .parse("Node[1-2],node[2-3]"));
+        assert_eq!(
+            ParseOptions::new()
+                .parse("Node[1-2],node[2-3]")
+                .map(|xs| xs.len()),
+            Ok(4)
+        );
+    }
+
+    #[test]
+    fn test_fold_merge_padding() {
+        let hosts = [
+            "n1", "n01", "n001", "n2", "n9", "n10", "n100", "n1000", "m5", "m6",
+        ];
+
+        assert_eq!(
+            FoldOptions::new().fold(hosts),
+            "m[5-6],n[01,001,1-2,9-10,100,1000]"
+        );
+        assert_eq!(
+            FoldOptions::new().merge_padding(true).fold(hosts),
+            "m[5-6],n[001-002,009-010,100,1000]"
+        );
+        assert_eq!(
+            FoldOptions::new()
+                .merge_padding(true)
+                .fold(["a1.x", "a01.y", "a2.x"]),
+            "a[1-2].x,a01.y"
+        );
+    }
+
+    #[test]
+    fn test_max_range_width() {
+        let options = ParseOptions::new().max_range_width(100);
+
+        assert_debug_snapshot!(options.parse("node[0-100000]"));
+        assert_debug_snapshot!(options.parse("r[1-2]n[1-60,70-120]"));
+        assert_eq!(
+            options.parse("r[1-100]n[1-100]").map(|xs| xs.len()),
+            Ok(10000)
+        );
+    }
+
+    #[test]
+    fn test_max_input_len() {
+        let options = ParseOptions::new().max_input_len(16);
+
+        assert_eq!(
+            options.parse("node[1-2]"),
+            Ok(vec!["node1".into(), "node2".into()])
+        );
+        assert_eq!(options.parse("oss[1-4].local,m").map(|xs| xs.len()), Ok(5));
+        assert_eq!(
+            options.parse(&"[".repeat(1 << 20)),
+            Err(Error::InputTooLong {
+                len: 1 << 20,
+                limit: 16
+            })
+        );
+        assert_debug_snapshot!(options.parse("oss[1-4].local,mds1"));
+    }
+
+    #[test]
+    fn test_radix() {
+        let octal = ParseOptions::new().radix(8);
+
+        assert_debug_snapshot!(octal.parse("node[6-11],node[006-010]"));
+        assert_debug_snapshot!(octal.parse("node[1-8]"));
+        assert_debug_snapshot!(octal.parse("node[1,9]"));
+
+        let hex = ParseOptions::new().radix(16);
+
+        assert_debug_snapshot!(hex.parse("gpu[8-B,0e-11],n[1a,FF]"));
+
+        assert_eq!(
+            octal.sort(true).parse("n[10,7]"),
+            Ok(vec!["n7".into(), "n10".into()])
+        );
+        assert_eq!(
+            ParseOptions::new().radix(10).parse("node[08-10]"),
+            ParseOptions::new().parse("node[08-10]")
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "radix must be 8, 10 or 16")]
+    fn test_radix_unsupported() {
+        ParseOptions::new().radix(2);
+    }
+
+    #[test]
+    fn test_signed() {
+        let signed = ParseOptions::new().signed(true);
+
+        assert_debug_snapshot!(signed.parse("temp[-2-2],t[-5--3,-1],x[1--1]"));
+        assert_debug_snapshot!(signed.parse("t[-02-02],n[2..-1]"));
+        assert_eq!(
+            signed.parse("t[-9223372036854775808,9223372036854775807]"),
+            Ok(vec![
+                "t-9223372036854775808".into(),
+                "t9223372036854775807".into()
+            ])
+        );
+        assert!(signed.parse("t[9223372036854775808]").is_err());
+        assert_eq!(
+            signed.clone().radix(16).parse("t[-a--8]"),
+            Ok(vec!["t-a".into(), "t-9".into(), "t-8".into()])
+        );
+        assert_eq!(
+            ParseOptions::new().parse("temp[1-3]-x"),
+            signed.parse("temp[1-3]-x")
+        );
+        assert!(ParseOptions::new().parse("temp[-2-2]").is_err());
+    }
+
+    #[test]
+    fn test_separators() {
+        let options = ParseOptions::new().range_sep('/').step_sep('%');
+
+        assert_eq!(
+            options.parse("node[1/3],node[10/14%2]"),
+            ParseOptions::new().parse("node[1-3],node[10-14:2]")
+        );
+        assert_eq!(
+            options.parse("node[1..=2]"),
+            Ok(vec!["node1".into(), "node2".into()])
+        );
+        assert_debug_snapshot!(options.parse("node[1-3]"));
+        assert_debug_snapshot!(options.parse("node[1/3,4..6]"));
+
+        let swapped = ParseOptions::new()
+            .step_sep('/')
+            .range_sep(':')
+            .step_sep('-');
+
+        assert_eq!(
+            swapped.parse("node[1:5-2]"),
+            Ok(vec!["node1".into(), "node3".into(), "node5".into()])
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "':' cannot be used as a separator: it is already the step separator"
+    )]
+    fn test_range_sep_conflict() {
+        let _ = ParseOptions::new().range_sep(':');
+    }
+
+    #[test]
+    #[should_panic(expected = "',' cannot be used as a separator")]
+    fn test_step_sep_unsupported() {
+        let _ = ParseOptions::new().step_sep(',');
+    }
+
+    #[test]
+    fn test_try_separators() {
+        let options = ParseOptions::new()
+            .try_range_sep('/')
+            .and_then(|x| x.try_step_sep('%'))
+            .unwrap();
+
+        assert_eq!(
+            options.parse("node[1/5%2]"),
+            Ok(vec!["node1".into(), "node3".into(), "node5".into()])
+        );
+        assert_eq!(
+            ParseOptions::new().try_range_sep(':').unwrap_err(),
+            Error::InvalidSeparator {
+                sep: ':',
+                reason: "it is already the step separator"
+            }
+        );
+        assert_eq!(
+            ParseOptions::new()
+                .try_step_sep('-')
+                .unwrap_err()
+                .to_string(),
+            "'-' cannot be used as a separator: it is already the range separator"
+        );
+        assert_eq!(
+            ParseOptions::new()
+                .try_step_sep('[')
+                .unwrap_err()
+                .to_string(),
+            "'[' cannot be used as a separator: it is taken by the rest of the syntax"
+        );
+        assert_eq!(
+            ParseOptions::new()
+                .try_range_sep('x')
+                .unwrap_err()
+                .to_string(),
+            "'x' cannot be used as a separator: it is not ASCII punctuation"
+        );
+    }
+
+    #[test]
+    fn test_reverse_digits() {
+        let options = ParseOptions::new().reverse_digits(true);
+
+        assert_eq!(
+            options.parse("node[10-12]"),
+            Ok(vec!["node01".into(), "node11".into(), "node21".into()])
+        );
+        assert_debug_snapshot!(options.parse("n[08-10,123],r1n[5],x[100-300:100]-ib,mds"));
+        assert_eq!(
+            options.clone().signed(true).parse("t[-12,34]"),
+            Ok(vec!["t-21".into(), "t43".into()])
+        );
+        assert_eq!(
+            options.clone().radix(16).parse("h[1f]"),
+            Ok(vec!["hf1".into()])
+        );
+        assert_eq!(
+            options.clone().sort(true).parse("n[1-2,10]"),
+            Ok(vec!["n01".into(), "n1".into(), "n2".into()])
+        );
+        assert_eq!(
+            ParseOptions::new()
+                .reverse_digits(false)
+                .parse("node[10-12]"),
+            ParseOptions::new().parse("node[10-12]")
+        );
+    }
+
+    #[test]
+    fn test_numeric_dedup() {
+        let options = ParseOptions::new().numeric_dedup(true);
+
+        assert_eq!(options.parse("node[1,01,001]"), Ok(vec!["node1".into()]));
+        assert_eq!(
+            options.parse("r[01-02]n0[1-2],r1n[1-2],r[1-2]n1.0"),
+            Ok(vec![
+                "r01n01".into(),
+                "r01n02".into(),
+                "r02n01".into(),
+                "r02n02".into(),
+                "r1n1.0".into(),
+                "r2n1.0".into(),
+            ])
+        );
+        assert_debug_snapshot!(options
+            .clone()
+            .error_on_duplicate(true)
+            .parse("node[08-10],node9"));
+        assert_eq!(
+            options
+                .case_insensitive_dedup(true)
+                .parse("Node01,node1,node10,node100"),
+            Ok(vec!["Node01".into(), "node10".into(), "node100".into()])
+        );
+        assert_eq!(
+            ParseOptions::new()
+                .parse("node[1,01,001]")
+                .map(|xs| xs.len()),
+            Ok(3)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "'[' cannot be used as a comment character")]
+    fn test_comment_unsupported() {
+        ReadOptions::new().comment(Some('['));
+    }
+
+    #[test]
+    #[should_panic(expected = "'-' cannot be used as a comment character")]
+    fn test_comment_range_sep() {
+        ReadOptions::new().comment(Some('-'));
+    }
+
+    #[test]
+    fn test_range_word() {
+        let options = ParseOptions::new().range_word("to");
+
+        assert_eq!(
+            options.parse("node[1 to 5]"),
+            Ok(vec![
+                "node1".into(),
+                "node2".into(),
+                "node3".into(),
+                "node4".into(),
+                "node5".into()
+            ])
+        );
+        assert_eq!(
+            options.parse("node[001 to 003]"),
+            Ok(vec!["node001".into(), "node002".into(), "node003".into()])
+        );
+        assert_eq!(
+            options.parse("n[3to1,7-8:1]"),
+            Ok(vec![
+                "n3".into(),
+                "n2".into(),
+                "n1".into(),
+                "n7".into(),
+                "n8".into()
+            ])
+        );
+        assert_eq!(
+            options.parse("[1,today]"),
+            ParseOptions::new().parse("[1,today]")
+        );
+        assert!(ParseOptions::new().parse("node[1 to 5]").is_err());
+        assert_debug_snapshot!(options.parse("node[1 tomato 5]"));
+    }
+
+    #[test]
+    #[should_panic(expected = "\"t-o\" cannot be used as a range word")]
+    fn test_range_word_unsupported() {
+        let _ = ParseOptions::new().range_word("t-o");
+    }
+
+    #[test]
+    fn test_strict() {
+        let strict = ParseOptions::new().strict(true);
+
+        assert_debug_snapshot!(strict.parse(",node1"));
+        assert_debug_snapshot!(strict.parse("node1,"));
+        assert_debug_snapshot!(strict.parse("node1 garbage"));
+        assert_debug_snapshot!(strict.parse("oss[1-2] .local"));
+        assert_eq!(
+            strict.parse(" oss[1-2].local , mds1"),
+            Ok(vec![
+                "oss1.local".into(),
+                "oss2.local".into(),
+                "mds1".into()
+            ])
+        );
+        assert_eq!(
+            ParseOptions::new().parse("node1 garbage"),
+            Ok(vec!["node1garbage".into()])
+        );
+    }
+
+    #[test]
+    fn test_parse_bytes() {
+        let options = ParseOptions::new();
+
+        assert_eq!(
+            options.parse_bytes(b"node[1-2]"),
+            Ok(vec!["node1".into(), "node2".into()])
+        );
+        assert_eq!(
+            options.parse_bytes(b"node[1-2],n\xff1"),
+            Err(Error::InvalidUtf8 { offset: 11 })
+        );
+        assert_eq!(
+            options
+                .clone()
+                .lossy_utf8(true)
+                .parse_bytes(b"node1,\"n\xff1\""),
+            Ok(vec!["node1".into(), "n\u{fffd}1".into()])
+        );
+        assert!(matches!(
+            options.lossy_utf8(true).parse_bytes(b"n\xff1"),
+            Err(Error::Parse(_))
+        ));
+    }
+}
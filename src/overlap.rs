@@ -0,0 +1,90 @@
+// Copyright (c) 2022 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+use crate::{expand, parse_hostlists, Error, Hostlist};
+
+/// Two comma separated entries of an expression that produce some of the same hosts. `first` and
+/// `second` are their indices, with `first < second`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Overlap {
+    pub first: usize,
+    pub second: usize,
+    /// The hosts both entries produce.
+    pub hosts: Hostlist,
+}
+
+/// Expands `input` exactly like [`crate::parse`], and also reports every pair of comma separated
+/// entries that produce the same hosts, as when a node is listed under two roles by mistake.
+///
+/// Entries are compared on their ranges, so the report does not expand them. Hosts are only the
+/// same when their padding is, as for deduplication. A host repeated within a single entry is not
+/// reported.
+///
+/// ```
+/// use hostlist_parser::parse_overlaps;
+///
+/// let (hosts, overlaps) = parse_overlaps("compute[1-4],gpu[1-2],compute[4-5]").unwrap();
+///
+/// assert_eq!(hosts.len(), 7);
+/// assert_eq!(overlaps.len(), 1);
+/// assert_eq!((overlaps[0].first, overlaps[0].second), (0, 2));
+/// assert_eq!(overlaps[0].hosts.to_string(), "compute4");
+/// ```
+pub fn parse_overlaps(input: &str) -> Result<(Vec<String>, Vec<Overlap>), Error> {
+    let hosts = parse_hostlists(input)?;
+    let segments: Vec<_> = hosts.iter().map(|x| Hostlist::from_parts(x)).collect();
+    let mut overlaps = vec![];
+
+    for (first, a) in segments.iter().enumerate() {
+        for (second, b) in segments.iter().enumerate().skip(first + 1) {
+            let hosts = a.intersection(b);
+
+            if !hosts.is_empty() {
+                overlaps.push(Overlap {
+                    first,
+                    second,
+                    hosts,
+                });
+            }
+        }
+    }
+
+    Ok((expand(hosts), overlaps))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use insta::assert_debug_snapshot;
+
+    #[test]
+    fn test_parse_overlaps() {
+        let input = "hostname[1,2-3].iml[2,3].com,hostname[3,4,5].iml[3,4].com";
+        let (hosts, overlaps) = parse_overlaps(input).unwrap();
+
+        assert_eq!(hosts, parse(input).unwrap());
+        assert_debug_snapshot!(overlaps
+            .iter()
+            .map(|x| (x.first, x.second, x.hosts.to_string()))
+            .collect::<Vec<_>>());
+
+        let (_, overlaps) = parse_overlaps("n[1-3],n[01-03],m1,n[2-3],m1,n[1,1]").unwrap();
+        let pairs: Vec<_> = overlaps
+            .iter()
+            .map(|x| (x.first, x.second, x.hosts.to_string()))
+            .collect();
+
+        assert_eq!(
+            pairs,
+            vec![
+                (0, 3, "n[2-3]".to_string()),
+                (0, 5, "n1".to_string()),
+                (2, 4, "m1".to_string()),
+            ]
+        );
+        assert!(matches!(parse_overlaps("n[1-"), Err(Error::Parse(_))));
+    }
+}
@@ -0,0 +1,116 @@
+// Copyright (c) 2022 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+use crate::{
+    parse_hostlists,
+    structures::{bracket_indices, cardinality, locate, Part, RangeOutput},
+    Error,
+};
+use itertools::Itertools as _;
+
+/// A piece of an expanded host, lined up with the expression it came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    /// Text written outside of brackets.
+    Literal(String),
+    /// A value taken from a bracket group, and the number of characters it was rendered with.
+    Index { value: u64, width: usize },
+}
+
+/// An expanded host, along with the pieces it was built from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpandedHost {
+    pub text: String,
+    pub segments: Vec<Segment>,
+}
+
+fn expanded_host_at(parts: &[Part], index: u128) -> ExpandedHost {
+    let mut indices = bracket_indices(parts, index).into_iter();
+    let mut text = String::new();
+    let mut segments = vec![];
+
+    for p in parts {
+        match p {
+            Part::String(x) => {
+                text.push_str(x);
+                segments.push(Segment::Literal(x.clone()));
+            }
+            Part::Range(xs) => {
+                let (x, i) = locate(xs, indices.next().unwrap());
+
+                // A word is text, whichever bracket group it came from.
+                if let RangeOutput::Words(words) = x {
+                    text.push_str(&words[i as usize]);
+                    segments.push(Segment::Literal(words[i as usize].clone()));
+
+                    continue;
+                }
+
+                let (value, suffix) = x.value_at(i);
+                let formatted = x.format_at(i);
+                let width = formatted.len() - suffix.map_or(0, str::len);
+
+                text.push_str(&formatted);
+                segments.push(Segment::Index { value, width });
+
+                // An alpha suffix on a disjoint member follows its index.
+                if let Some(suffix) = suffix {
+                    segments.push(Segment::Literal(suffix.to_string()));
+                }
+            }
+        }
+    }
+
+    ExpandedHost { text, segments }
+}
+
+/// Expands `input` like [`crate::parse`], keeping the literal and numeric pieces of each host.
+///
+/// ```
+/// use hostlist_parser::{parse_parts, Segment};
+///
+/// let hosts = parse_parts("oss[1-2]-ib[0-1].local").unwrap();
+///
+/// assert_eq!(hosts[1].text, "oss1-ib1.local");
+/// assert_eq!(hosts[1].segments[3], Segment::Index { value: 1, width: 1 });
+/// ```
+pub fn parse_parts(input: &str) -> Result<Vec<ExpandedHost>, Error> {
+    let hosts = parse_hostlists(input)?;
+
+    Ok(hosts
+        .iter()
+        .flat_map(|parts| (0..cardinality(parts)).map(move |i| expanded_host_at(parts, i)))
+        .unique_by(|x| x.text.clone())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use insta::assert_debug_snapshot;
+
+    #[test]
+    fn test_parse_parts() {
+        assert_debug_snapshot!(parse_parts("oss[1-2]-ib[0-1].local"));
+        assert_debug_snapshot!(parse_parts("node[09-10,7a],mds1,node09"));
+        assert_eq!(
+            parse_parts("[web,db]1").unwrap()[1].segments,
+            vec![Segment::Literal("db".into()), Segment::Literal("1".into())]
+        );
+    }
+
+    #[test]
+    fn test_parse_parts_matches_parse() {
+        let input = "hostname[1,2-3].iml[2,3].com,hostname[3,4,5].iml[3,4].com";
+
+        assert_eq!(
+            parse_parts(input)
+                .unwrap()
+                .into_iter()
+                .map(|x| x.text)
+                .collect::<Vec<_>>(),
+            crate::parse(input).unwrap()
+        );
+    }
+}
@@ -0,0 +1,335 @@
+// Copyright (c) 2022 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+pub(crate) type Iter<'a> = std::iter::Copied<std::slice::Iter<'a, (u64, u64)>>;
+
+/// A set of `u64`s stored as sorted, non-overlapping, non-adjacent inclusive ranges.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct RangeSet(Vec<(u64, u64)>);
+
+impl RangeSet {
+    pub(crate) fn single(value: u64) -> Self {
+        RangeSet(vec![(value, value)])
+    }
+
+    /// Adds `lo..=hi`, merging it with any range it overlaps or touches.
+    pub(crate) fn insert(&mut self, lo: u64, hi: u64) {
+        let start = self
+            .0
+            .partition_point(|&(_, end)| end.saturating_add(1) < lo);
+        let end = self
+            .0
+            .partition_point(|&(start, _)| start <= hi.saturating_add(1));
+
+        let (lo, hi) = if start < end {
+            (lo.min(self.0[start].0), hi.max(self.0[end - 1].1))
+        } else {
+            (lo, hi)
+        };
+
+        self.0.splice(start..end, [(lo, hi)]);
+    }
+
+    pub(crate) fn contains(&self, value: u64) -> bool {
+        let i = self.0.partition_point(|&(_, end)| end < value);
+
+        self.0.get(i).is_some_and(|&(start, _)| start <= value)
+    }
+
+    /// The number of values smaller than `value`, if it is in the set.
+    pub(crate) fn rank(&self, value: u64) -> Option<u128> {
+        let i = self.0.partition_point(|&(_, end)| end < value);
+        let &(start, _) = self.0.get(i).filter(|&&(start, _)| start <= value)?;
+
+        let before: u128 = self.0[..i]
+            .iter()
+            .map(|&(lo, hi)| u128::from(hi - lo) + 1)
+            .sum();
+
+        Some(before + u128::from(value - start))
+    }
+
+    /// Removes `lo..=hi`, splitting any range it falls inside of.
+    pub(crate) fn remove(&mut self, lo: u64, hi: u64) {
+        let start = self.0.partition_point(|&(_, end)| end < lo);
+        let end = self.0.partition_point(|&(start, _)| start <= hi);
+
+        if start >= end {
+            return;
+        }
+
+        let (first, last) = (self.0[start], self.0[end - 1]);
+        let mut kept = vec![];
+
+        if first.0 < lo {
+            kept.push((first.0, lo - 1));
+        }
+
+        if last.1 > hi {
+            kept.push((hi + 1, last.1));
+        }
+
+        self.0.splice(start..end, kept);
+    }
+
+    /// Keeps only the values `f` accepts, called once per value in order. The kept runs are
+    /// rebuilt in a single pass, so removing scattered values does not shift the ranges each time.
+    pub(crate) fn retain(&mut self, mut f: impl FnMut(u64) -> bool) {
+        let mut kept: Vec<(u64, u64)> = vec![];
+
+        for value in self.0.iter().flat_map(|&(lo, hi)| lo..=hi) {
+            if !f(value) {
+                continue;
+            }
+
+            match kept.last_mut() {
+                Some((_, hi)) if *hi + 1 == value => *hi = value,
+                _ => kept.push((value, value)),
+            }
+        }
+
+        self.0 = kept;
+    }
+
+    /// The values in this set but not in `other`.
+    pub(crate) fn difference(&self, other: &RangeSet) -> RangeSet {
+        let mut set = self.clone();
+
+        for &(lo, hi) in &other.0 {
+            set.remove(lo, hi);
+        }
+
+        set
+    }
+
+    /// The values in both sets.
+    pub(crate) fn intersection(&self, other: &RangeSet) -> RangeSet {
+        RangeSet(self.overlaps(other).collect())
+    }
+
+    /// Whether no value is in both sets. Stops at the first shared range.
+    pub(crate) fn is_disjoint(&self, other: &RangeSet) -> bool {
+        self.overlaps(other).next().is_none()
+    }
+
+    /// The ranges of values in both sets, in order, found by walking the two sets together.
+    fn overlaps<'a>(&'a self, other: &'a RangeSet) -> impl Iterator<Item = (u64, u64)> + 'a {
+        let (mut a, mut b) = (self.0.iter().peekable(), other.0.iter().peekable());
+
+        std::iter::from_fn(move || {
+            while let (Some(&&(lo_a, hi_a)), Some(&&(lo_b, hi_b))) = (a.peek(), b.peek()) {
+                let (lo, hi) = (lo_a.max(lo_b), hi_a.min(hi_b));
+
+                if hi_a < hi_b {
+                    a.next();
+                } else {
+                    b.next();
+                }
+
+                if lo <= hi {
+                    return Some((lo, hi));
+                }
+            }
+
+            None
+        })
+    }
+
+    /// Removes and returns the `count` smallest values, or all of them if there are fewer.
+    pub(crate) fn take_first(&mut self, mut count: u128) -> RangeSet {
+        let mut i = 0;
+        let mut split = None;
+
+        while count > 0 && i < self.0.len() {
+            let (lo, hi) = self.0[i];
+            let len = u128::from(hi - lo) + 1;
+
+            if len > count {
+                split = Some((lo, lo + (count - 1) as u64));
+                self.0[i].0 = lo + count as u64;
+                break;
+            }
+
+            count -= len;
+            i += 1;
+        }
+
+        let mut taken: Vec<_> = self.0.drain(..i).collect();
+        taken.extend(split);
+
+        RangeSet(taken)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The number of values in the set.
+    pub(crate) fn len(&self) -> u128 {
+        self.0.iter().map(|&(lo, hi)| u128::from(hi - lo) + 1).sum()
+    }
+
+    pub(crate) fn iter(&self) -> Iter<'_> {
+        self.0.iter().copied()
+    }
+}
+
+impl IntoIterator for RangeSet {
+    type Item = (u64, u64);
+    type IntoIter = std::vec::IntoIter<(u64, u64)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(xs: &[(u64, u64)]) -> RangeSet {
+        let mut set = RangeSet::default();
+
+        for &(lo, hi) in xs {
+            set.insert(lo, hi);
+        }
+
+        set
+    }
+
+    #[test]
+    fn test_insert() {
+        assert_eq!(set(&[(1, 3), (7, 9)]).0, vec![(1, 3), (7, 9)]);
+        assert_eq!(set(&[(7, 9), (1, 3)]).0, vec![(1, 3), (7, 9)]);
+        assert_eq!(set(&[(1, 3), (4, 6)]).0, vec![(1, 6)]);
+        assert_eq!(set(&[(1, 3), (7, 9), (2, 8)]).0, vec![(1, 9)]);
+        assert_eq!(
+            set(&[(1, 3), (7, 9), (5, 5)]).0,
+            vec![(1, 3), (5, 5), (7, 9)]
+        );
+        assert_eq!(set(&[(5, 10), (6, 7)]).0, vec![(5, 10)]);
+        assert_eq!(
+            set(&[(0, 1), (u64::MAX - 1, u64::MAX), (2, u64::MAX - 2)]).0,
+            vec![(0, u64::MAX)]
+        );
+    }
+
+    #[test]
+    fn test_contains() {
+        let x = set(&[(1, 3), (7, 9)]);
+
+        assert!(x.contains(1) && x.contains(3) && x.contains(8));
+        assert!(!x.contains(0) && !x.contains(5) && !x.contains(10));
+        assert!(!RangeSet::default().contains(0));
+    }
+
+    #[test]
+    fn test_rank() {
+        let x = set(&[(1, 3), (7, 9), (20, 20)]);
+
+        assert_eq!(x.rank(1), Some(0));
+        assert_eq!(x.rank(8), Some(4));
+        assert_eq!(x.rank(20), Some(6));
+        assert_eq!(x.rank(5), None);
+        assert_eq!(x.rank(21), None);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut x = set(&[(1, 100)]);
+
+        x.remove(40, 45);
+        assert_eq!(x.0, vec![(1, 39), (46, 100)]);
+
+        x.remove(30, 50);
+        assert_eq!(x.0, vec![(1, 29), (51, 100)]);
+
+        x.remove(200, 300);
+        x.remove(0, 1);
+        assert_eq!(x.0, vec![(2, 29), (51, 100)]);
+
+        x.remove(0, u64::MAX);
+        assert!(x.is_empty());
+
+        assert_eq!(
+            set(&[(1, 10), (20, 30)])
+                .difference(&set(&[(5, 5), (8, 25)]))
+                .0,
+            vec![(1, 4), (6, 7), (26, 30)]
+        );
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut x = set(&[(1, 10), (20, 22)]);
+
+        x.retain(|x| x != 4 && x != 21);
+        assert_eq!(x.0, vec![(1, 3), (5, 10), (20, 20), (22, 22)]);
+
+        x.retain(|x| x % 2 == 0);
+        assert_eq!(
+            x.0,
+            vec![(2, 2), (6, 6), (8, 8), (10, 10), (20, 20), (22, 22)]
+        );
+
+        x.retain(|_| false);
+        assert!(x.is_empty());
+
+        let mut x = set(&[(u64::MAX - 2, u64::MAX)]);
+
+        x.retain(|x| x != u64::MAX - 2);
+        assert_eq!(x.0, vec![(u64::MAX - 1, u64::MAX)]);
+    }
+
+    #[test]
+    fn test_intersection() {
+        assert_eq!(
+            set(&[(1, 100)]).intersection(&set(&[(90, 200)])).0,
+            vec![(90, 100)]
+        );
+        assert_eq!(
+            set(&[(1, 5), (10, 20), (30, 40)])
+                .intersection(&set(&[(4, 12), (15, 35)]))
+                .0,
+            vec![(4, 5), (10, 12), (15, 20), (30, 35)]
+        );
+        assert!(set(&[(1, 5)]).intersection(&set(&[(6, 9)])).is_empty());
+        assert!(set(&[(1, 5)]).intersection(&RangeSet::default()).is_empty());
+    }
+
+    #[test]
+    fn test_is_disjoint() {
+        assert!(!set(&[(1, 100)]).is_disjoint(&set(&[(90, 200)])));
+        assert!(!set(&[(1, 5), (30, 40)]).is_disjoint(&set(&[(6, 29), (40, 50)])));
+        assert!(set(&[(1, 5), (30, 40)]).is_disjoint(&set(&[(6, 29), (41, 50)])));
+        assert!(set(&[(1, 5)]).is_disjoint(&RangeSet::default()));
+    }
+
+    #[test]
+    fn test_take_first() {
+        let mut x = set(&[(1, 3), (7, 9), (20, 20)]);
+
+        assert_eq!(x.take_first(0).0, vec![]);
+        assert_eq!(x.take_first(2).0, vec![(1, 2)]);
+        assert_eq!(x.take_first(3).0, vec![(3, 3), (7, 8)]);
+        assert_eq!(x.0, vec![(9, 9), (20, 20)]);
+        assert_eq!(x.take_first(5).0, vec![(9, 9), (20, 20)]);
+        assert!(x.is_empty());
+
+        let mut x = set(&[(0, u64::MAX)]);
+
+        assert_eq!(
+            x.take_first(u128::from(u64::MAX)).0,
+            vec![(0, u64::MAX - 1)]
+        );
+        assert_eq!(x.0, vec![(u64::MAX, u64::MAX)]);
+    }
+
+    #[test]
+    fn test_len() {
+        assert_eq!(RangeSet::default().len(), 0);
+        assert_eq!(set(&[(1, 5), (3, 8), (10, 10)]).len(), 9);
+        assert_eq!(set(&[(0, u64::MAX)]).len(), u128::from(u64::MAX) + 1);
+    }
+}
@@ -0,0 +1,263 @@
+// Copyright (c) 2022 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+use crate::{parse_hostlists, Error, Expansion, ReadOptions};
+use std::{
+    collections::HashSet,
+    io::{BufRead, ErrorKind},
+};
+
+/// Parses hostlist expressions from `r` as they are read, yielding hosts in the same order as
+/// [`crate::parse`] would for the whole input.
+///
+/// The input is split into entries at each newline, and at each comma outside of a bracket group
+/// or quoted host, so an unclosed bracket cannot swallow the lines after it. Each entry is expanded
+/// lazily before the next one is read. Blank entries are skipped, as is everything from a `#`
+/// outside a quoted host to the end of its line; see [`crate::ReadOptions`] to change or disable
+/// the comment character. Repeated hosts are dropped across the whole input, so every host yielded
+/// so far is remembered.
+///
+/// An entry that fails to parse yields an error, with positions counted in bytes from the start of
+/// the input, and reading carries on with the next entry. A failed read yields [`Error::Io`] and
+/// ends the iterator.
+///
+/// ```
+/// use hostlist_parser::parse_read;
+///
+/// let hosts: Vec<_> = parse_read("# servers\noss[1-2],mds1 # metadata\nmds1\n".as_bytes())
+///     .collect::<Result<_, _>>()
+///     .unwrap();
+///
+/// assert_eq!(hosts, vec!["oss1", "oss2", "mds1"]);
+/// ```
+pub fn parse_read(r: impl BufRead) -> impl Iterator<Item = Result<String, Error>> {
+    ReadOptions::new().parse_read(r)
+}
+
+pub(crate) struct ReadHosts<R> {
+    reader: R,
+    /// The ASCII byte starting a comment, if any.
+    comment: Option<u8>,
+    /// The byte offset of the next entry in the input.
+    offset: usize,
+    hosts: Option<Expansion>,
+    seen: HashSet<String>,
+    done: bool,
+}
+
+impl<R: BufRead> ReadHosts<R> {
+    pub(crate) fn new(reader: R, comment: Option<u8>) -> Self {
+        Self {
+            reader,
+            comment,
+            offset: 0,
+            hosts: None,
+            seen: HashSet::new(),
+            done: false,
+        }
+    }
+
+    /// Reads the next entry, without its separator or comment, along with the number of bytes
+    /// consumed. `None` at the end of the input.
+    fn read_entry(&mut self) -> Result<Option<(Vec<u8>, usize)>, Error> {
+        let mut entry = vec![];
+        let mut consumed = 0;
+        let (mut depth, mut quoted, mut escaped, mut comment) = (0usize, false, false, false);
+
+        loop {
+            let buf = match self.reader.fill_buf() {
+                Ok(buf) => buf,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    return Err(Error::Io {
+                        message: err.to_string(),
+                    })
+                }
+            };
+
+            if buf.is_empty() {
+                return Ok((!entry.is_empty()).then_some((entry, consumed)));
+            }
+
+            let mut kept = 0;
+            let end = buf.iter().position(|&b| {
+                match b {
+                    b'\n' => return true,
+                    _ if comment => return false,
+                    _ if escaped => escaped = false,
+                    b'\\' if quoted => escaped = true,
+                    b'"' => quoted = !quoted,
+                    _ if quoted => {}
+                    _ if Some(b) == self.comment => {
+                        comment = true;
+
+                        return false;
+                    }
+                    b'[' => depth += 1,
+                    b']' => depth = depth.saturating_sub(1),
+                    b',' if depth == 0 => return true,
+                    _ => {}
+                }
+
+                kept += 1;
+
+                false
+            });
+
+            let used = end.map_or(buf.len(), |end| end + 1);
+
+            entry.extend_from_slice(&buf[..kept]);
+            consumed += used;
+            self.reader.consume(used);
+
+            if end.is_some() {
+                return Ok(Some((entry, consumed)));
+            }
+        }
+    }
+
+    /// Parses the next non-blank entry. `None` at the end of the input.
+    fn next_hosts(&mut self) -> Option<Result<Expansion, Error>> {
+        loop {
+            let (entry, consumed) = match self.read_entry() {
+                Ok(entry) => entry?,
+                Err(err) => {
+                    self.done = true;
+
+                    return Some(Err(err));
+                }
+            };
+            let offset = self.offset;
+
+            self.offset += consumed;
+
+            let entry = match std::str::from_utf8(&entry) {
+                Ok(entry) => entry,
+                Err(err) => {
+                    return Some(Err(Error::InvalidUtf8 {
+                        offset: offset + err.valid_up_to(),
+                    }))
+                }
+            };
+            let trimmed = entry.trim_start();
+            let offset = offset + entry.len() - trimmed.len();
+            let trimmed = trimmed.trim_end();
+
+            if trimmed.is_empty() {
+                continue;
+            }
+
+            return Some(parse_hostlists(trimmed).map(Expansion::new).map_err(|err| {
+                let mut err = err.map_range(|r| r.to_string());
+
+                err.position += offset;
+
+                Error::Parse(err)
+            }));
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for ReadHosts<R> {
+    type Item = Result<String, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(hosts) = &mut self.hosts {
+                match hosts.find(|x| !self.seen.contains(x)) {
+                    Some(host) => {
+                        self.seen.insert(host.clone());
+
+                        return Some(Ok(host));
+                    }
+                    None => self.hosts = None,
+                }
+            }
+
+            if self.done {
+                return None;
+            }
+
+            match self.next_hosts()? {
+                Ok(hosts) => self.hosts = Some(hosts),
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use insta::assert_debug_snapshot;
+    use std::io::{self, BufReader, Read};
+
+    #[test]
+    fn test_parse_read() {
+        let input = "oss[1-3].local, mds[1,2]\n\n\"weird,name\"\r\noss2.local,n[01-02]";
+        let hosts: Result<Vec<_>, _> = parse_read(input.as_bytes()).collect();
+
+        assert_eq!(
+            hosts.unwrap(),
+            parse("oss[1-3].local,mds[1,2],\"weird,name\",oss2.local,n[01-02]").unwrap()
+        );
+
+        // Entries are found across buffer boundaries.
+        let hosts: Result<Vec<_>, _> =
+            parse_read(BufReader::with_capacity(2, input.as_bytes())).collect();
+
+        assert_eq!(hosts.unwrap().len(), 8);
+    }
+
+    #[test]
+    fn test_parse_read_comments() {
+        let input = "# inventory\noss[1-2] # the, servers\n\"#weird\",n[1-2] #\n  # x\nmds1";
+        let hosts: Result<Vec<_>, _> = parse_read(input.as_bytes()).collect();
+
+        assert_eq!(
+            hosts.unwrap(),
+            vec!["oss1", "oss2", "#weird", "n1", "n2", "mds1"]
+        );
+
+        let hosts: Result<Vec<_>, _> = ReadOptions::new()
+            .comment(Some(';'))
+            .parse_read("n1 ; n2\n#n3".as_bytes())
+            .collect();
+
+        assert!(hosts.is_err());
+
+        let hosts: Result<Vec<_>, _> = ReadOptions::new()
+            .comment(None)
+            .parse_read("n1 # n2".as_bytes())
+            .collect();
+
+        assert!(hosts.is_err());
+        assert_debug_snapshot!(parse_read("n1 # c, d\nn[1-".as_bytes()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_parse_read_errors() {
+        assert_debug_snapshot!(parse_read("node1\n  node[1-\nnode2".as_bytes()).collect::<Vec<_>>());
+        assert_eq!(
+            parse_read(&b"node1,no\xffde"[..]).collect::<Vec<_>>(),
+            vec![Ok("node1".into()), Err(Error::InvalidUtf8 { offset: 8 })]
+        );
+
+        struct Failing;
+
+        impl Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("disk on fire"))
+            }
+        }
+
+        assert_eq!(
+            parse_read(BufReader::new(Failing)).collect::<Vec<_>>(),
+            vec![Err(Error::Io {
+                message: "disk on fire".into()
+            })]
+        );
+    }
+}
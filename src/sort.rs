@@ -0,0 +1,158 @@
+// Copyright (c) 2022 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+use std::cmp::Ordering;
+
+/// Splits `s` into maximal runs of ASCII digits and non-digits.
+fn runs(s: &str) -> impl Iterator<Item = &str> {
+    let mut rest = s;
+
+    std::iter::from_fn(move || {
+        let digit = rest.chars().next()?.is_ascii_digit();
+        let end = rest
+            .find(|c: char| c.is_ascii_digit() != digit)
+            .unwrap_or(rest.len());
+        let (run, tail) = rest.split_at(end);
+
+        rest = tail;
+
+        Some(run)
+    })
+}
+
+fn is_digits(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// Compares literal runs so that `.` sorts before any other character, which orders
+/// domains label by label.
+fn cmp_literal(a: &str, b: &str) -> Ordering {
+    let key = |c: char| if c == '.' { 0 } else { u32::from(c) + 1 };
+
+    a.chars().map(key).cmp(b.chars().map(key))
+}
+
+/// Natural ordering of hostnames: digit runs compare by numeric value and literal runs compare
+/// label-wise. Numbers of equal value are tie-broken by padding, so `node01` sorts before `node1`.
+///
+/// This is the order [`crate::ParseOptions::sort`] produces, and it is total: hosts only compare
+/// equal when they are the same string.
+///
+/// ```
+/// use hostlist_parser::cmp_hosts;
+/// use std::cmp::Ordering;
+///
+/// assert_eq!(cmp_hosts("node2", "node10"), Ordering::Less);
+/// assert_eq!(cmp_hosts("node01", "node1"), Ordering::Less);
+/// ```
+pub fn cmp_hosts(a: &str, b: &str) -> Ordering {
+    let mut padding = Ordering::Equal;
+    let mut xs = runs(a);
+    let mut ys = runs(b);
+
+    loop {
+        let ord = match (xs.next(), ys.next()) {
+            (None, None) => return padding.then_with(|| a.cmp(b)),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if is_digits(x) && is_digits(y) => {
+                let (xt, yt) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                let ord = xt.len().cmp(&yt.len()).then_with(|| xt.cmp(yt));
+
+                if ord == Ordering::Equal && padding == Ordering::Equal {
+                    padding = y.len().cmp(&x.len());
+                }
+
+                ord
+            }
+            (Some(x), Some(y)) => cmp_literal(x, y),
+        };
+
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+}
+
+/// Sorts `hosts` in place by [`cmp_hosts`].
+///
+/// ```
+/// use hostlist_parser::sort_hosts;
+///
+/// let mut hosts = vec!["node10".to_string(), "node9".to_string(), "node09".to_string()];
+///
+/// sort_hosts(&mut hosts);
+///
+/// assert_eq!(hosts, vec!["node09", "node9", "node10"]);
+/// ```
+pub fn sort_hosts(hosts: &mut [String]) {
+    hosts.sort_by(|a, b| cmp_hosts(a, b));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ParseOptions;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_runs() {
+        assert_eq!(
+            runs("oss01-ib2.local").collect::<Vec<_>>(),
+            vec!["oss", "01", "-ib", "2", ".local"]
+        );
+        assert_eq!(runs("").count(), 0);
+    }
+
+    #[test]
+    fn test_cmp_hosts() {
+        assert_eq!(cmp_hosts("node2", "node10"), Ordering::Less);
+        assert_eq!(cmp_hosts("node01", "node1"), Ordering::Less);
+        assert_eq!(cmp_hosts("node01", "node2"), Ordering::Less);
+        assert_eq!(cmp_hosts("node1", "node1"), Ordering::Equal);
+        assert_eq!(cmp_hosts("node", "node1"), Ordering::Less);
+        assert_eq!(cmp_hosts("a.z.com", "a-b.com"), Ordering::Less);
+        assert_eq!(cmp_hosts("node1.b", "node1.a"), Ordering::Greater);
+        assert_eq!(
+            cmp_hosts(
+                "node99999999999999999999999",
+                "node100000000000000000000000"
+            ),
+            Ordering::Less
+        );
+    }
+
+    fn host() -> impl Strategy<Value = String> {
+        "([a-c.-]{0,2}0{0,2}[0-9]{0,3}){1,3}"
+    }
+
+    proptest! {
+        #[test]
+        fn prop_cmp_hosts_total_order(a in host(), b in host(), c in host()) {
+            prop_assert_eq!(cmp_hosts(&a, &b), cmp_hosts(&b, &a).reverse());
+            prop_assert_eq!(cmp_hosts(&a, &b) == Ordering::Equal, a == b);
+
+            if cmp_hosts(&a, &b) != Ordering::Greater && cmp_hosts(&b, &c) != Ordering::Greater {
+                prop_assert_ne!(cmp_hosts(&a, &c), Ordering::Greater);
+            }
+        }
+
+        #[test]
+        fn prop_sort_hosts_matches_sorted_parse(
+            xs in prop::collection::vec(("[a-c]{1,2}", 0..3usize, 0..200u32), 1..20)
+        ) {
+            let mut hosts: Vec<_> = xs
+                .iter()
+                .map(|(prefix, width, n)| format!("{prefix}{n:0width$}"))
+                .collect();
+            let sorted = ParseOptions::new().sort(true).parse(&hosts.join(",")).unwrap();
+
+            hosts.sort();
+            hosts.dedup();
+            sort_hosts(&mut hosts);
+
+            prop_assert_eq!(hosts, sorted);
+        }
+    }
+}
@@ -2,14 +2,355 @@
 // Use of this source code is governed by a MIT-style
 // license that can be found in the LICENSE file.
 
+use std::{num::ParseIntError, ops::RangeInclusive};
+
+/// How the numbers in bracket groups are written: their radix, whether they may be negative, and
+/// whether their digits are written in reverse.
+///
+/// Signed numbers are stored offset by `2^63`, which keeps their order and the distance between
+/// them as `u64`s, so ranges of them work unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Numbers {
+    pub(crate) radix: u32,
+    pub(crate) signed: bool,
+    pub(crate) reverse_digits: bool,
+}
+
+const SIGN_OFFSET: u64 = 1 << 63;
+
+/// The widest padding a `u64` can need.
+pub(crate) const MAX_WIDTH: usize = 20;
+
+impl Numbers {
+    pub(crate) const DECIMAL: Numbers = Numbers {
+        radix: 10,
+        signed: false,
+        reverse_digits: false,
+    };
+
+    /// Reads the magnitude `digits`, negated if `negative`.
+    pub(crate) fn parse(&self, negative: bool, digits: &str) -> Result<u64, ParseIntError> {
+        if !self.signed {
+            return u64::from_str_radix(digits, self.radix);
+        }
+
+        let x = if negative {
+            i64::from_str_radix(&format!("-{digits}"), self.radix)?
+        } else {
+            i64::from_str_radix(digits, self.radix)?
+        };
+
+        Ok(x as u64 ^ SIGN_OFFSET)
+    }
+
+    /// The sign and the digits of `num`, without padding.
+    fn digits(&self, num: u64) -> (&'static str, String) {
+        let (sign, magnitude) = if self.signed {
+            let x = (num ^ SIGN_OFFSET) as i64;
+
+            (if x < 0 { "-" } else { "" }, x.unsigned_abs())
+        } else {
+            ("", num)
+        };
+        let digits = match self.radix {
+            8 => format!("{magnitude:o}"),
+            16 => format!("{magnitude:x}"),
+            _ => magnitude.to_string(),
+        };
+
+        (sign, digits)
+    }
+
+    /// Reads a number written by [`Numbers::format`], whatever its padding. Anything else may read
+    /// as a number too, so the result is only a candidate to check.
+    fn read(&self, s: &str) -> Option<u64> {
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(x) if self.signed => (true, x),
+            _ => (false, s),
+        };
+
+        if self.reverse_digits {
+            self.parse(negative, &digits.chars().rev().collect::<String>())
+        } else {
+            self.parse(negative, digits)
+        }
+        .ok()
+    }
+
+    /// The number of digits in `num`, without its sign.
+    pub(crate) fn num_digits(&self, num: u64) -> usize {
+        self.digits(num).1.len()
+    }
+
+    /// Writes `num` padded as by [`format_num_prefix`]. The sign of a negative number comes before
+    /// its padding.
+    fn format(&self, num: u64, prefix: usize, same_prefix_len: bool) -> String {
+        let (sign, digits) = self.digits(num);
+        let width = if same_prefix_len {
+            prefix + digits.len()
+        } else {
+            prefix + 1
+        };
+
+        let padded = format!("{digits:0>width$}");
+
+        if self.reverse_digits {
+            format!("{sign}{}", padded.chars().rev().collect::<String>())
+        } else {
+            format!("{sign}{padded}")
+        }
+    }
+}
+
+/// How bracket groups are written: their numbers, the separators of ranges and steps, and a word
+/// that may also separate a range. A `strict` syntax rejects whitespace inside a host rather than
+/// dropping it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Syntax {
+    pub(crate) numbers: Numbers,
+    pub(crate) range_sep: char,
+    pub(crate) step_sep: char,
+    pub(crate) range_word: Option<&'static str>,
+    pub(crate) strict: bool,
+}
+
+impl Syntax {
+    pub(crate) const DEFAULT: Syntax = Syntax {
+        numbers: Numbers::DECIMAL,
+        range_sep: '-',
+        step_sep: ':',
+        range_word: None,
+        strict: false,
+    };
+}
+
 #[derive(Debug, Clone)]
 pub(crate) enum RangeOutput {
     Range(usize, bool, u64, u64),
     RangeReversed(usize, bool, u64, u64),
-    Disjoint(Vec<(usize, u64)>),
+    Disjoint(Vec<(usize, u64, Option<String>)>),
+    /// Every `step`th value of a `Range` or `RangeReversed`, starting from its first value.
+    Stepped(Box<RangeOutput>, u64),
+    /// Literal alternatives, as in `[web,db]`. They fill a bracket group on their own.
+    Words(Vec<String>),
 }
 
 impl RangeOutput {
+    /// The smallest and largest value covered, regardless of the written order. Words have no
+    /// value, so they give `(u64::MAX, u64::MIN)`.
+    pub(crate) fn bounds(&self) -> (u64, u64) {
+        match self {
+            RangeOutput::Range(_, _, start, end) | RangeOutput::RangeReversed(_, _, start, end) => {
+                (*start, *end)
+            }
+            RangeOutput::Disjoint(xs) => {
+                xs.iter().fold((u64::MAX, u64::MIN), |(lo, hi), (_, x, _)| {
+                    (lo.min(*x), hi.max(*x))
+                })
+            }
+            RangeOutput::Stepped(..) => {
+                let first = self.value_at(0).0;
+                let last = self.value_at((self.cardinality() - 1) as u64).0;
+
+                (first.min(last), first.max(last))
+            }
+            RangeOutput::Words(_) => (u64::MAX, u64::MIN),
+        }
+    }
+
+    /// The number of values this range expands to, whichever way it is written. This is a `u128`
+    /// as a range over every `u64` holds one more value than a `u64` can count.
+    pub(crate) fn cardinality(&self) -> u128 {
+        match self {
+            RangeOutput::Range(_, _, start, end) | RangeOutput::RangeReversed(_, _, start, end) => {
+                u128::from(end - start) + 1
+            }
+            RangeOutput::Disjoint(xs) => xs.len() as u128,
+            RangeOutput::Words(xs) => xs.len() as u128,
+            RangeOutput::Stepped(x, step) => (x.cardinality() - 1) / u128::from(*step) + 1,
+        }
+    }
+
+    /// The numeric value and suffix at `index` in expansion order, where
+    /// `index < self.cardinality()`.
+    pub(crate) fn value_at(&self, index: u64) -> (u64, Option<&str>) {
+        match self {
+            RangeOutput::Range(_, _, start, _) => (start + index, None),
+            RangeOutput::RangeReversed(_, _, _, start) => (start - index, None),
+            RangeOutput::Disjoint(xs) => {
+                let (_, x, suffix) = &xs[index as usize];
+
+                (*x, suffix.as_deref())
+            }
+            RangeOutput::Stepped(x, step) => x.value_at(index * step),
+            RangeOutput::Words(_) => unreachable!("words have no numeric value"),
+        }
+    }
+
+    /// Formats the value at `index` in expansion order, where `index < self.cardinality()`.
+    pub(crate) fn format_at(&self, index: u64) -> String {
+        self.format_at_with(index, Numbers::DECIMAL)
+    }
+
+    /// Like [`RangeOutput::format_at`], writing the value as `numbers`.
+    pub(crate) fn format_at_with(&self, index: u64, numbers: Numbers) -> String {
+        match self {
+            RangeOutput::Range(prefix, same_prefix_len, start, _) => {
+                numbers.format(start + index, *prefix, *same_prefix_len)
+            }
+            RangeOutput::RangeReversed(prefix, same_prefix_len, _, start) => {
+                numbers.format(start - index, *prefix, *same_prefix_len)
+            }
+            RangeOutput::Disjoint(xs) => {
+                let (prefix, x, suffix) = &xs[index as usize];
+
+                format_member(*x, *prefix, suffix.as_deref(), numbers)
+            }
+            RangeOutput::Stepped(x, step) => x.format_at_with(index * step, numbers),
+            RangeOutput::Words(xs) => xs[index as usize].clone(),
+        }
+    }
+
+    /// The index in expansion order of the first value written as `s`, if there is one.
+    pub(crate) fn position(&self, s: &str, numbers: Numbers) -> Option<u64> {
+        let index = match self {
+            RangeOutput::Range(_, _, start, end) => {
+                let x = numbers.read(s).filter(|x| (start..=end).contains(&x))?;
+
+                x - start
+            }
+            RangeOutput::RangeReversed(_, _, end, start) => {
+                let x = numbers.read(s).filter(|x| (end..=start).contains(&x))?;
+
+                start - x
+            }
+            RangeOutput::Stepped(x, step) => {
+                let index = x.position(s, numbers)?;
+
+                return index.is_multiple_of(*step).then(|| index / step);
+            }
+            RangeOutput::Disjoint(xs) => {
+                return xs
+                    .iter()
+                    .position(|(prefix, x, suffix)| {
+                        format_member(*x, *prefix, suffix.as_deref(), numbers) == s
+                    })
+                    .map(|i| i as u64);
+            }
+            RangeOutput::Words(xs) => return xs.iter().position(|x| x == s).map(|i| i as u64),
+        };
+
+        // The number read may be padded differently, or not written as `numbers` would write it.
+        (self.format_at_with(index, numbers) == s).then_some(index)
+    }
+
+    /// This range without `value` written with `zeros` leading zeros, as `!05` is, split around it
+    /// in expansion order. A value written with other padding is a different host, so it is kept.
+    pub(crate) fn without(&self, value: u64, zeros: usize, numbers: Numbers) -> Vec<RangeOutput> {
+        let written = numbers.format(value, zeros, true);
+        let matches =
+            |prefix, same_prefix_len| numbers.format(value, prefix, same_prefix_len) == written;
+
+        match *self {
+            RangeOutput::Range(prefix, same_prefix_len, start, end)
+                if (start..=end).contains(&value) && matches(prefix, same_prefix_len) =>
+            {
+                let range = |start, end| RangeOutput::Range(prefix, same_prefix_len, start, end);
+
+                [
+                    (value > start).then(|| range(start, value - 1)),
+                    (value < end).then(|| range(value + 1, end)),
+                ]
+                .into_iter()
+                .flatten()
+                .collect()
+            }
+            RangeOutput::RangeReversed(prefix, same_prefix_len, end, start)
+                if (end..=start).contains(&value) && matches(prefix, same_prefix_len) =>
+            {
+                let range =
+                    |end, start| RangeOutput::RangeReversed(prefix, same_prefix_len, end, start);
+
+                [
+                    (value < start).then(|| range(value + 1, start)),
+                    (value > end).then(|| range(end, value - 1)),
+                ]
+                .into_iter()
+                .flatten()
+                .collect()
+            }
+            RangeOutput::Disjoint(ref xs) => {
+                let xs: Vec<_> = xs
+                    .iter()
+                    .filter(|(prefix, x, suffix)| {
+                        *x != value
+                            || format_member(*x, *prefix, suffix.as_deref(), numbers) != written
+                    })
+                    .cloned()
+                    .collect();
+
+                if xs.is_empty() {
+                    vec![]
+                } else {
+                    vec![RangeOutput::Disjoint(xs)]
+                }
+            }
+            RangeOutput::Stepped(ref x, step) => {
+                let stepped = |x| RangeOutput::Stepped(Box::new(x), step);
+
+                match **x {
+                    RangeOutput::Range(prefix, same_prefix_len, start, end)
+                        if (start..=end).contains(&value)
+                            && (value - start).is_multiple_of(step)
+                            && matches(prefix, same_prefix_len) =>
+                    {
+                        let range = |start, end| {
+                            stepped(RangeOutput::Range(prefix, same_prefix_len, start, end))
+                        };
+
+                        [
+                            (value > start).then(|| range(start, value - step)),
+                            value
+                                .checked_add(step)
+                                .filter(|x| *x <= end)
+                                .map(|x| range(x, end)),
+                        ]
+                        .into_iter()
+                        .flatten()
+                        .collect()
+                    }
+                    RangeOutput::RangeReversed(prefix, same_prefix_len, end, start)
+                        if (end..=start).contains(&value)
+                            && (start - value).is_multiple_of(step)
+                            && matches(prefix, same_prefix_len) =>
+                    {
+                        let range = |end, start| {
+                            stepped(RangeOutput::RangeReversed(
+                                prefix,
+                                same_prefix_len,
+                                end,
+                                start,
+                            ))
+                        };
+
+                        [
+                            (value < start).then(|| range(value + step, start)),
+                            value
+                                .checked_sub(step)
+                                .filter(|x| *x >= end)
+                                .map(|x| range(end, x)),
+                        ]
+                        .into_iter()
+                        .flatten()
+                        .collect()
+                    }
+                    _ => vec![self.clone()],
+                }
+            }
+            _ => vec![self.clone()],
+        }
+    }
+
     pub(crate) fn iter(&self) -> RangeOutputIter {
         match self {
             RangeOutput::Range(prefix, same_prefix_len, start, end) => {
@@ -25,13 +366,31 @@ impl RangeOutput {
             RangeOutput::Disjoint(xs) => {
                 RangeOutputIter::Internal(Box::new(xs.clone().into_iter()))
             }
+            RangeOutput::Words(xs) => RangeOutputIter::Words(xs.clone().into_iter()),
+            RangeOutput::Stepped(x, _) => {
+                let (prefix, same_prefix_len) = match **x {
+                    RangeOutput::Range(prefix, same_prefix_len, ..)
+                    | RangeOutput::RangeReversed(prefix, same_prefix_len, ..) => {
+                        (prefix, same_prefix_len)
+                    }
+                    _ => unreachable!("only ranges are stepped"),
+                };
+                let this = self.clone();
+
+                RangeOutputIter::External(
+                    prefix,
+                    same_prefix_len,
+                    Box::new((0..self.cardinality()).map(move |i| this.value_at(i as u64).0)),
+                )
+            }
         }
     }
 }
 
 pub(crate) enum RangeOutputIter {
     External(usize, bool, Box<dyn Iterator<Item = u64>>),
-    Internal(Box<dyn Iterator<Item = (usize, u64)>>),
+    Internal(Box<dyn Iterator<Item = (usize, u64, Option<String>)>>),
+    Words(std::vec::IntoIter<String>),
 }
 
 impl Iterator for RangeOutputIter {
@@ -42,21 +401,47 @@ impl Iterator for RangeOutputIter {
             RangeOutputIter::External(prefix, same_prefix_len, xs) => xs
                 .next()
                 .map(|x| format_num_prefix(x, *prefix, *same_prefix_len)),
-            RangeOutputIter::Internal(xs) => xs
-                .next()
-                .map(|(prefix, x)| format_num_prefix(x, prefix, true)),
+            RangeOutputIter::Internal(xs) => xs.next().map(|(prefix, x, suffix)| {
+                format_member(x, prefix, suffix.as_deref(), Numbers::DECIMAL)
+            }),
+            RangeOutputIter::Words(xs) => xs.next(),
         }
     }
 }
 
+pub(crate) fn num_digits(num: u64) -> usize {
+    num.checked_ilog10().map_or(1, |x| x as usize + 1)
+}
+
+/// Splits `lo..=hi` into sub-ranges whose values share the same number of digits.
+pub(crate) fn digit_bands(
+    lo: u64,
+    hi: u64,
+) -> impl DoubleEndedIterator<Item = (usize, RangeInclusive<u64>)> {
+    (num_digits(lo)..=num_digits(hi)).map(move |digits| {
+        let min = match digits {
+            1 => 0,
+            _ => 10u64.pow(digits as u32 - 1),
+        };
+        let max = 10u64.checked_pow(digits as u32).map_or(u64::MAX, |x| x - 1);
+
+        (digits, lo.max(min)..=hi.min(max))
+    })
+}
+
 pub(crate) fn format_num_prefix(num: u64, prefix: usize, same_prefix_len: bool) -> String {
-    let width = if same_prefix_len {
-        prefix + num.to_string().len()
-    } else {
-        prefix + 1
-    };
+    Numbers::DECIMAL.format(num, prefix, same_prefix_len)
+}
+
+/// Formats a disjoint member, which keeps its own padding and optional alpha suffix.
+fn format_member(num: u64, prefix: usize, suffix: Option<&str>, numbers: Numbers) -> String {
+    let mut x = numbers.format(num, prefix, true);
+
+    if let Some(suffix) = suffix {
+        x.push_str(suffix);
+    }
 
-    format!("{num:0>width$}")
+    x
 }
 
 #[derive(Debug, Clone)]
@@ -78,6 +463,127 @@ pub(crate) fn flatten_ranges(xs: &[RangeOutput]) -> Vec<String> {
     xs.iter().flat_map(|x| x.iter()).collect()
 }
 
+/// The number of hosts a single hostlist expands to, before deduplication: the product of the
+/// cardinalities of its bracket groups, saturating at `u128::MAX`.
+pub(crate) fn cardinality(parts: &[Part]) -> u128 {
+    parts
+        .iter()
+        .filter_map(Part::get_ranges)
+        .map(|xs| xs.iter().map(RangeOutput::cardinality).sum::<u128>())
+        .fold(1u128, u128::saturating_mul)
+}
+
+/// Finds the range holding the value at `index` of a bracket group, and the offset within it.
+pub(crate) fn locate(xs: &[RangeOutput], mut index: u128) -> (&RangeOutput, u64) {
+    for x in xs {
+        let count = x.cardinality();
+
+        if index < count {
+            return (x, index as u64);
+        }
+
+        index -= count;
+    }
+
+    unreachable!("index out of bounds for bracket group")
+}
+
+/// Splits the `index` of a host within a single hostlist into an index per bracket group,
+/// following the cartesian product order (the last group varies fastest).
+pub(crate) fn bracket_indices(parts: &[Part], mut index: u128) -> Vec<u128> {
+    let mut xs: Vec<_> = parts
+        .iter()
+        .filter_map(Part::get_ranges)
+        .rev()
+        .map(|xs| {
+            let count = xs.iter().map(RangeOutput::cardinality).sum::<u128>();
+            let x = index % count;
+
+            index /= count;
+
+            x
+        })
+        .collect();
+
+    xs.reverse();
+
+    xs
+}
+
+/// Builds the host at `index` of a single hostlist.
+pub(crate) fn host_at(parts: &[Part], index: u128) -> String {
+    host_at_with(parts, index, Numbers::DECIMAL)
+}
+
+/// Like [`host_at`], writing numbers as `numbers`.
+pub(crate) fn host_at_with(parts: &[Part], index: u128, numbers: Numbers) -> String {
+    let mut indices = bracket_indices(parts, index).into_iter();
+    let mut s = String::new();
+
+    for p in parts {
+        match p {
+            Part::String(x) => s.push_str(x),
+            Part::Range(xs) => {
+                let (x, i) = locate(xs, indices.next().unwrap());
+
+                s.push_str(&x.format_at_with(i, numbers))
+            }
+        }
+    }
+
+    s
+}
+
+/// The index in a bracket group of the first value written as `s`, if there is one.
+fn group_position(xs: &[RangeOutput], s: &str, numbers: Numbers) -> Option<u128> {
+    let mut offset = 0u128;
+
+    for x in xs {
+        if let Some(i) = x.position(s, numbers) {
+            return Some(offset + u128::from(i));
+        }
+
+        offset += x.cardinality();
+    }
+
+    None
+}
+
+/// The index of the first host of a single hostlist written as `host`, if it produces it, in the
+/// order of [`host_at_with`]. Nothing is expanded: the host is matched against each part in turn,
+/// trying each way it can be split between bracket groups.
+pub(crate) fn first_index(parts: &[Part], host: &str, numbers: Numbers) -> Option<u128> {
+    let Some((part, rest)) = parts.split_first() else {
+        return host.is_empty().then_some(0);
+    };
+
+    let xs = match part {
+        Part::String(x) => return first_index(rest, host.strip_prefix(x.as_str())?, numbers),
+        Part::Range(xs) => xs,
+    };
+
+    // Earlier bracket groups vary slowest, so the first host takes the first value that leaves a
+    // match for the rest.
+    let (i, j) = (0..=host.len())
+        .filter(|end| host.is_char_boundary(*end))
+        .filter_map(|end| {
+            let i = group_position(xs, &host[..end], numbers)?;
+
+            Some((i, first_index(rest, &host[end..], numbers)?))
+        })
+        .min()?;
+
+    Some(i.saturating_mul(cardinality(rest)).saturating_add(j))
+}
+
+pub(crate) fn bounds(xs: &[RangeOutput]) -> (u64, u64) {
+    xs.iter()
+        .map(RangeOutput::bounds)
+        .fold((u64::MAX, u64::MIN), |(lo, hi), (start, end)| {
+            (lo.min(start), hi.max(end))
+        })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -90,9 +596,161 @@ mod tests {
             .collect::<Vec<_>>());
     }
 
+    #[test]
+    fn test_range_output_bounds() {
+        assert_eq!(RangeOutput::Range(0, true, 1, 10).bounds(), (1, 10));
+        assert_eq!(RangeOutput::RangeReversed(0, true, 3, 9).bounds(), (3, 9));
+        assert_eq!(
+            RangeOutput::Disjoint(vec![(0, 5, None), (0, 1, None), (1, 9, None)]).bounds(),
+            (1, 9)
+        );
+    }
+
+    #[test]
+    fn test_cardinality() {
+        assert_eq!(RangeOutput::Range(0, true, 1, 10).cardinality(), 10);
+        assert_eq!(RangeOutput::RangeReversed(0, true, 3, 9).cardinality(), 7);
+        assert_eq!(
+            RangeOutput::Disjoint(vec![(0, 5, None), (0, 1, None)]).cardinality(),
+            2
+        );
+        assert_eq!(
+            RangeOutput::Range(0, true, 0, u64::MAX).cardinality(),
+            u128::from(u64::MAX) + 1
+        );
+        assert_eq!(
+            RangeOutput::Stepped(Box::new(RangeOutput::RangeReversed(0, true, 1, 10)), 4)
+                .cardinality(),
+            3
+        );
+        assert_eq!(
+            RangeOutput::Stepped(Box::new(RangeOutput::Range(0, true, 5, 5)), 7).cardinality(),
+            1
+        );
+
+        assert_eq!(
+            cardinality(&[
+                Part::String("node".into()),
+                Part::Range(vec![
+                    RangeOutput::Range(0, true, 1, 4),
+                    RangeOutput::Disjoint(vec![(0, 9, None)])
+                ]),
+                Part::String("-ib".into()),
+                Part::Range(vec![RangeOutput::Range(0, true, 0, 1)]),
+            ]),
+            10
+        );
+        assert_eq!(cardinality(&[Part::String("mds1".into())]), 1);
+
+        let full = || Part::Range(vec![RangeOutput::Range(0, true, 0, u64::MAX)]);
+
+        assert_eq!(cardinality(&[full(), full(), full()]), u128::MAX);
+    }
+
+    #[test]
+    fn test_host_at() {
+        let parts = [
+            Part::String("node".into()),
+            Part::Range(vec![
+                RangeOutput::RangeReversed(0, true, 1, 2),
+                RangeOutput::Disjoint(vec![(1, 5, Some("a".into()))]),
+            ]),
+            Part::String("-ib".into()),
+            Part::Range(vec![RangeOutput::Range(0, true, 0, 1)]),
+        ];
+
+        assert_debug_snapshot!((0..cardinality(&parts))
+            .map(|i| host_at(&parts, i))
+            .collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_first_index() {
+        let parts = [
+            Part::String("n".into()),
+            Part::Range(vec![
+                RangeOutput::Range(1, false, 8, 10),
+                RangeOutput::Disjoint(vec![(0, 9, None), (0, 1, Some("a".into()))]),
+            ]),
+            Part::String("-".into()),
+            Part::Range(vec![RangeOutput::Stepped(
+                Box::new(RangeOutput::RangeReversed(0, true, 1, 5)),
+                2,
+            )]),
+        ];
+        let index = |host| first_index(&parts, host, Numbers::DECIMAL);
+
+        assert_eq!(index("n08-5"), Some(0));
+        assert_eq!(index("n10-1"), Some(8));
+        assert_eq!(index("n9-3"), Some(10));
+        assert_eq!(index("n1a-1"), Some(14));
+        assert_eq!(index("n8-5"), None);
+        assert_eq!(index("n09-4"), None);
+        assert_eq!(index("n10-1x"), None);
+
+        // `122` is both `1` then `22` and `12` then `2`; the first comes earlier.
+        let parts = [
+            Part::Range(vec![RangeOutput::Disjoint(vec![
+                (0, 12, None),
+                (0, 1, None),
+            ])]),
+            Part::Range(vec![RangeOutput::Disjoint(vec![
+                (0, 2, None),
+                (0, 22, None),
+            ])]),
+        ];
+
+        assert_eq!(first_index(&parts, "122", Numbers::DECIMAL), Some(0));
+        assert_eq!(first_index(&parts, "1222", Numbers::DECIMAL), Some(1));
+
+        let hex = Numbers {
+            radix: 16,
+            signed: true,
+            reverse_digits: true,
+        };
+        let parts = [Part::Range(vec![RangeOutput::Range(
+            1,
+            true,
+            1 << 63,
+            (1 << 63) + 32,
+        )])];
+
+        assert_eq!(first_index(&parts, "a0", hex), Some(10));
+        assert_eq!(first_index(&parts, "-1", hex), None);
+        assert_eq!(first_index(&parts, "A0", hex), None);
+        assert_eq!(first_index(&parts, "a", hex), None);
+    }
+
+    #[test]
+    fn test_range_output_stepped() {
+        let x = RangeOutput::Stepped(Box::new(RangeOutput::Range(1, false, 4, 11)), 3);
+
+        assert_eq!(x.cardinality(), 3);
+        assert_eq!(x.bounds(), (4, 10));
+        assert_eq!(x.iter().collect::<Vec<_>>(), vec!["04", "07", "10"]);
+
+        let x = RangeOutput::Stepped(Box::new(RangeOutput::RangeReversed(0, true, 4, 10)), 2);
+
+        assert_eq!(x.cardinality(), 4);
+        assert_eq!(x.bounds(), (4, 10));
+        assert_eq!(x.format_at(1), "8");
+        assert_eq!(x.iter().collect::<Vec<_>>(), vec!["10", "8", "6", "4"]);
+    }
+
+    #[test]
+    fn test_range_output_disjoint_suffix_iter() {
+        assert_debug_snapshot!(RangeOutput::Disjoint(vec![
+            (0, 1, Some("a".into())),
+            (1, 2, Some("b".into())),
+            (0, 3, None)
+        ])
+        .iter()
+        .collect::<Vec<_>>());
+    }
+
     #[test]
     fn test_range_output_disjoint_iter() {
-        assert_debug_snapshot!(RangeOutput::Disjoint(vec![(0, 1), (1, 10)])
+        assert_debug_snapshot!(RangeOutput::Disjoint(vec![(0, 1, None), (1, 10, None)])
             .iter()
             .collect::<Vec<_>>());
     }
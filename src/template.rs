@@ -0,0 +1,270 @@
+// Copyright (c) 2022 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+use crate::{parse_parts, parse_range, structures::flatten_ranges, Error, Segment};
+use itertools::Itertools as _;
+use std::collections::HashSet;
+
+/// A piece of a template: text copied as is, or the name between `{` and `}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Piece<'a> {
+    Literal(String),
+    Field(&'a str),
+}
+
+fn invalid(reason: impl Into<String>) -> Error {
+    Error::InvalidTemplate {
+        reason: reason.into(),
+    }
+}
+
+/// Splits `template` into literal text and `{field}`s. `{{` and `}}` stand for literal braces.
+pub(crate) fn parse_template(template: &str) -> Result<Vec<Piece<'_>>, Error> {
+    let mut pieces = vec![];
+    let mut literal = String::new();
+    let mut rest = template;
+
+    while let Some(i) = rest.find(['{', '}']) {
+        literal.push_str(&rest[..i]);
+
+        let (brace, after) = (&rest[i..=i], &rest[i + 1..]);
+
+        if let Some(after) = after.strip_prefix(brace) {
+            literal.push_str(brace);
+            rest = after;
+            continue;
+        }
+
+        if brace == "}" {
+            return Err(invalid(format!(
+                "unmatched `}}` at byte {}, write `}}}}` for a literal brace",
+                template.len() - rest.len() + i
+            )));
+        }
+
+        let end = after.find('}').ok_or_else(|| {
+            invalid(format!(
+                "unclosed `{{` at byte {}, write `{{{{` for a literal brace",
+                template.len() - rest.len() + i
+            ))
+        })?;
+
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+        }
+
+        pieces.push(Piece::Field(&after[..end]));
+        rest = &after[end + 1..];
+    }
+
+    literal.push_str(rest);
+
+    if !literal.is_empty() {
+        pieces.push(Piece::Literal(literal));
+    }
+
+    Ok(pieces)
+}
+
+/// Expands `groups`, each a single bracket group such as `[1-4]`, into hosts written with
+/// `template`: `{0}` is filled with a value of the first group, `{1}` with one of the second, and
+/// so on. `{{` and `}}` write literal braces.
+///
+/// Hosts come in the same order as [`crate::parse`] would give for the groups written in a row,
+/// with the last group varying fastest, and repeats are dropped. A placeholder may be used more
+/// than once, but every group must be used, and every placeholder must name a group.
+///
+/// To write each host of a whole expression with a template, see [`map_template`].
+///
+/// ```
+/// use hostlist_parser::expand_template;
+///
+/// assert_eq!(
+///     expand_template("rack{0}-node{1}", &["[1-2]", "[3-4]"]).unwrap(),
+///     vec!["rack1-node3", "rack1-node4", "rack2-node3", "rack2-node4"]
+/// );
+/// assert!(expand_template("rack{0}-node{2}", &["[1-2]", "[3-4]"]).is_err());
+/// ```
+pub fn expand_template(template: &str, groups: &[&str]) -> Result<Vec<String>, Error> {
+    let pieces = parse_template(template)?;
+    let mut used = vec![false; groups.len()];
+
+    for piece in &pieces {
+        if let Piece::Field(field) = piece {
+            let i = field
+                .parse::<usize>()
+                .ok()
+                .filter(|&i| i < groups.len())
+                .ok_or_else(|| match groups.len() {
+                    0 => invalid(format!("`{{{field}}}` is used, but no groups are given")),
+                    n => invalid(format!(
+                        "`{{{field}}}` does not name a group, expected `{{0}}` to `{{{}}}`",
+                        n - 1
+                    )),
+                })?;
+
+            used[i] = true;
+        }
+    }
+
+    if let Some(i) = used.iter().position(|x| !x) {
+        return Err(invalid(format!("group {i} is not used by any placeholder")));
+    }
+
+    let values = groups
+        .iter()
+        .map(|x| Ok(flatten_ranges(&parse_range(x)?)))
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let fill = |xs: &[String]| {
+        pieces
+            .iter()
+            .map(|piece| match piece {
+                Piece::Literal(x) => x.as_str(),
+                Piece::Field(i) => &xs[i.parse::<usize>().unwrap()],
+            })
+            .collect::<String>()
+    };
+
+    if values.is_empty() {
+        return Ok(vec![fill(&[])]);
+    }
+
+    let mut seen = HashSet::new();
+
+    Ok(values
+        .into_iter()
+        .multi_cartesian_product()
+        .map(|xs| fill(&xs))
+        .filter(|x| seen.insert(x.clone()))
+        .collect())
+}
+
+/// The placeholders [`map_template`] fills.
+const HOST_FIELDS: [&str; 3] = ["host", "index", "short"];
+
+/// Expands `expr` like [`crate::parse`], and writes each host with `template`, as for an
+/// inventory file. `{{` and `}}` write literal braces.
+///
+/// - `{host}` is the host.
+/// - `{short}` is the host up to its first `.`.
+/// - `{index}` is the value the host takes from the first bracket group of its entry, without
+///   padding, or nothing for a host written without brackets.
+///
+/// This takes the expression first, unlike [`expand_template`], which fills a template from bare
+/// bracket groups rather than from the hosts of an expression, hence the different name.
+///
+/// ```
+/// use hostlist_parser::map_template;
+///
+/// assert_eq!(
+///     map_template("oss[01-02].local", "{host} ansible_user=root rack={index}").unwrap(),
+///     vec![
+///         "oss01.local ansible_user=root rack=1",
+///         "oss02.local ansible_user=root rack=2",
+///     ]
+/// );
+/// assert_eq!(map_template("mds1.local", "{{{short}}}").unwrap(), vec!["{mds1}"]);
+/// ```
+pub fn map_template(expr: &str, template: &str) -> Result<Vec<String>, Error> {
+    let pieces = parse_template(template)?;
+
+    if let Some(field) = pieces.iter().find_map(|x| match x {
+        Piece::Field(x) if !HOST_FIELDS.contains(x) => Some(x),
+        _ => None,
+    }) {
+        return Err(invalid(format!(
+            "unknown placeholder `{{{field}}}`, expected one of {}",
+            HOST_FIELDS.iter().map(|x| format!("`{{{x}}}`")).join(", ")
+        )));
+    }
+
+    Ok(parse_parts(expr)?
+        .into_iter()
+        .map(|host| {
+            let index = host.segments.iter().find_map(|x| match x {
+                Segment::Index { value, .. } => Some(value.to_string()),
+                Segment::Literal(_) => None,
+            });
+
+            pieces
+                .iter()
+                .map(|piece| match piece {
+                    Piece::Literal(x) => x.clone(),
+                    Piece::Field("host") => host.text.clone(),
+                    Piece::Field("short") => host.text.split('.').next().unwrap().to_string(),
+                    Piece::Field(_) => index.clone().unwrap_or_default(),
+                })
+                .collect()
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use insta::assert_debug_snapshot;
+
+    #[test]
+    fn test_parse_template() {
+        assert_eq!(
+            parse_template("a{0}b{{c}}{x}").unwrap(),
+            vec![
+                Piece::Literal("a".into()),
+                Piece::Field("0"),
+                Piece::Literal("b{c}".into()),
+                Piece::Field("x"),
+            ]
+        );
+        assert_eq!(parse_template("").unwrap(), vec![]);
+        assert_debug_snapshot!(parse_template("a{0"));
+        assert_debug_snapshot!(parse_template("a}b"));
+    }
+
+    #[test]
+    fn test_expand_template() {
+        assert_eq!(
+            expand_template("n{1}{{r{0}}}-{1}", &["[01,3]", "[2-1]"]).unwrap(),
+            vec!["n2{r01}-2", "n1{r01}-1", "n2{r3}-2", "n1{r3}-1"]
+        );
+        assert_eq!(
+            expand_template("n{0}", &["[1-2,1]"]).unwrap(),
+            vec!["n1", "n2"]
+        );
+        assert_eq!(expand_template("mds", &[]).unwrap(), vec!["mds"]);
+        assert_debug_snapshot!(expand_template("n{0}{2}", &["[1]", "[2]"]));
+        assert_debug_snapshot!(expand_template("n{0}", &["[1]", "[2]"]));
+        assert_debug_snapshot!(expand_template("n{x}", &["[1]"]));
+        assert_debug_snapshot!(expand_template("n{0}", &["[1-"]));
+        assert_debug_snapshot!(expand_template("n{0}", &["1-2"]));
+        assert_debug_snapshot!(expand_template("n{0}", &[]));
+    }
+
+    #[test]
+    fn test_map_template() {
+        assert_eq!(
+            map_template("r[1-2]n[08-09].ib,mds1,r1n08.ib", "{short}:{index}:{host}").unwrap(),
+            vec![
+                "r1n08:1:r1n08.ib",
+                "r1n09:1:r1n09.ib",
+                "r2n08:2:r2n08.ib",
+                "r2n09:2:r2n09.ib",
+                "mds1::mds1",
+            ]
+        );
+        assert_eq!(
+            map_template("n[1-2]", "{{ {index} }}").unwrap(),
+            vec!["{ 1 }", "{ 2 }"]
+        );
+        assert_debug_snapshot!(map_template("n[1-2]", "{host} {rack}"));
+        assert!(matches!(
+            map_template("n[1-2]", "{host"),
+            Err(Error::InvalidTemplate { .. })
+        ));
+        assert!(matches!(
+            map_template("n[1-", "{host}"),
+            Err(Error::Parse(_))
+        ));
+    }
+}
@@ -0,0 +1,24 @@
+// Copyright (c) 2022 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+use crate::parse;
+
+/// Parses `input` like [`parse`], flattening any error into a human-readable message that includes
+/// the position of the failure. The plain `String` error makes this trivial to expose through
+/// `wasm-bindgen`.
+pub fn parse_js(input: &str) -> Result<Vec<String>, String> {
+    parse(input).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use insta::assert_debug_snapshot;
+
+    #[test]
+    fn test_parse_js() {
+        assert_debug_snapshot!(parse_js("oss[1,2].local"));
+        assert_debug_snapshot!(parse_js("hostname[1"));
+    }
+}